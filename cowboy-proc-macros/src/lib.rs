@@ -80,6 +80,12 @@ pub fn derive_command(input: TokenStream) -> TokenStream {
         Ok((variants, mode)) => {
             let enum_name = &input.ident;
             let left_side_for_match = prepare_left_side_for_match_statement(&variants);
+            let variant_name = variants.iter().map(|variant| variant.ident.to_string());
+            let mode_variant = mode.iter().map(|mode| match *mode {
+                "write_with_response" => quote! { WriteWithResponse },
+                _ => quote! { WriteOnly },
+            });
+
             TokenStream::from(quote! {
                 impl #enum_name {
                     /// Get the write mode for a given command.
@@ -88,6 +94,12 @@ pub fn derive_command(input: TokenStream) -> TokenStream {
                             #(Self::#left_side_for_match => crate::CmdMode::from_str(#mode).unwrap()),*
                         }
                     }
+
+                    /// Get the name and write mode of every command, in
+                    /// declaration order.
+                    pub fn modes() -> &'static [(&'static str, crate::CmdMode)] {
+                        &[#((#variant_name, crate::CmdMode::#mode_variant)),*]
+                    }
                 }
             })
         }
@@ -166,7 +178,7 @@ fn parse_mode_attribute(attribute: &Attribute) -> Result<Option<&'static str>, E
 
 fn prepare_left_side_for_match_statement(variants: &[Variant]) -> Vec<proc_macro2::TokenStream> {
     variants
-        .into_iter()
+        .iter()
         .map(|variant| {
             let variant_ident = &variant.ident;
             match &variant.fields {
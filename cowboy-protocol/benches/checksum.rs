@@ -0,0 +1,47 @@
+//! Times the frame checksum over a frame body and over a 1 KB buffer.
+//!
+//! Compare the bitwise and table-driven CRCs with
+//! `cargo bench --bench checksum` and
+//! `cargo bench --bench checksum --features crc-table`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use cowboy_protocol::frame::Checksum;
+
+const ITERATIONS: u32 = 100_000;
+
+fn bench(
+    name: &str,
+    data: &[u8],
+) {
+    // Warm up caches, and the table if it is used.
+    for _ in 0..ITERATIONS / 10 {
+        black_box(Checksum::of(black_box(data)));
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(Checksum::of(black_box(data)));
+    }
+    let per_iter = start.elapsed() / ITERATIONS;
+
+    println!("{name:<12} {:>10.1?} / iter", per_iter);
+}
+
+fn main() {
+    let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+
+    let mut buffer = [0x0; 1024];
+    for (i, b) in buffer.iter_mut().enumerate() {
+        *b = (i * 31 % 251) as u8;
+    }
+
+    let crc = match cfg!(feature = "crc-table") {
+        true => "table",
+        false => "bitwise",
+    };
+    println!("checksum ({crc})");
+    bench("9 B body", &body);
+    bench("1 KB", &buffer);
+}
@@ -0,0 +1,40 @@
+//! Encodes common commands and prints their frames.
+//!
+//! Run it with `cargo run --example encode`.
+
+use cowboy_protocol::error::CmdError;
+use cowboy_protocol::frame::annotate;
+use cowboy_protocol::frame::Frame;
+use cowboy_protocol::types::FieldWeakening;
+use cowboy_protocol::types::HallInterpolation;
+use cowboy_protocol::types::Speed;
+use cowboy_protocol::types::TorqueGain;
+use cowboy_protocol::types::TorqueMode;
+use cowboy_protocol::SettingsWriteCmd;
+
+fn main() -> Result<(), CmdError> {
+    use SettingsWriteCmd::*;
+
+    let cmds = [
+        SetLight(true),
+        SetAutoLock(true),
+        SetMaxAssistedSpeed(Speed::kmh(25)?),
+        SetFieldWeakening(FieldWeakening::new(20)?),
+        SetHallInterpolation(HallInterpolation::new(10)?),
+        SetTorqueGain(TorqueGain::new(30)?),
+        SetMotorTorqueMode(TorqueMode { speed_limit: true }),
+        ReadMaxAssistedSpeed,
+        WriteFlash,
+        CloseFlash,
+    ];
+
+    for cmd in cmds {
+        let frame = Frame::from_command(&cmd)?;
+
+        println!("{cmd:?} ({})", frame.mode());
+        println!("  {frame}");
+        println!("  {}", annotate(frame.as_bytes()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! Queues commands of different types together and sends them in order.
+//!
+//! Run it with `cargo run --example schedule`.
+
+use cowboy_protocol::cmd::CowboyCharacteristic;
+use cowboy_protocol::cmd::CowboyLockCmd;
+use cowboy_protocol::error::CmdError;
+use cowboy_protocol::frame::Frame;
+use cowboy_protocol::transport::Command;
+use cowboy_protocol::CowboyService;
+use cowboy_protocol::SettingsWriteCmd;
+
+fn main() -> Result<(), CmdError> {
+    let unlock = CowboyService::Cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::SetLock(false)));
+
+    let pending: Vec<Box<dyn Command>> = vec![
+        Box::new(unlock),
+        Box::new(SettingsWriteCmd::SetLight(true)),
+        Box::new(SettingsWriteCmd::ReadMaxAssistedSpeed),
+    ];
+
+    for cmd in &pending {
+        let frame = Frame::from_command(cmd.as_ref())?;
+        println!(
+            "{} {} ({}): {frame}",
+            cmd.service(),
+            cmd.characteristic(),
+            frame.mode()
+        );
+    }
+
+    Ok(())
+}
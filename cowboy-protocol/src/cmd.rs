@@ -9,15 +9,46 @@ use uuid::Uuid;
 
 use crate::cmd;
 use crate::error::CmdError;
+use crate::error::FieldError;
+use crate::mode::CmdMode;
+use crate::registers::Register;
+use crate::registers::AUTO_LOCK;
+use crate::registers::DENIED_READS;
+use crate::registers::FIELD_WEAKENING;
+use crate::registers::FLASH;
+use crate::registers::HALL_INTERPOLATION;
+use crate::registers::LIGHT;
+use crate::registers::MAX_ASSISTED_SPEED;
+use crate::registers::TORQUE_GAIN;
+use crate::registers::TORQUE_MODE;
 use crate::types::FieldWeakening;
 use crate::types::HallInterpolation;
 use crate::types::Speed;
 use crate::types::TorqueGain;
 use crate::types::TorqueMode;
+use crate::utils::value_u8;
+use crate::utils::verify_checksum;
 pub const DESCRIPTOR_CHARACTERISTIC_WRITE: Uuid = uuid!("6E400002-B5A3-F393-E0A9-E50E24DCCA9E");
 
+/// The Client Characteristic Configuration Descriptor (CCCD).
+///
+/// Writing to this descriptor of a characteristic (un)subscribes from its
+/// notifications.
+pub const DESCRIPTOR_CLIENT_CHARACTERISTIC_CONFIGURATION: Uuid =
+    uuid!("00002902-0000-1000-8000-00805F9B34FB");
+
+/// The CCCD value that enables notifications.
+pub const fn cccd_enable_notify() -> [u8; 2] {
+    [0x01, 0x00]
+}
+
+/// The CCCD value that disables notifications and indications.
+pub const fn cccd_disable() -> [u8; 2] {
+    [0x00, 0x00]
+}
+
 #[non_exhaustive]
-#[derive(Service, Debug, Clone, Copy)]
+#[derive(Service, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyService {
     #[service("6E400001-B5A3-F393-E0A9-E50E24DCCA9E")]
     Settings(SettingsCharacteristic),
@@ -27,7 +58,7 @@ pub enum CowboyService {
 }
 
 #[non_exhaustive]
-#[derive(Characteristic, Debug, Clone, Copy)]
+#[derive(Characteristic, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SettingsCharacteristic {
     /// Characteristic for modifying the settings of the Cowboy.
     #[characteristic("6E400002-B5A3-F393-E0A9-E50E24DCCA9E")]
@@ -39,7 +70,7 @@ pub enum SettingsCharacteristic {
 }
 
 #[non_exhaustive]
-#[derive(Characteristic, Debug, Clone, Copy)]
+#[derive(Characteristic, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyCharacteristic {
     /// Characteristic for locking/unlocking the Cowboy.
     #[characteristic("C0B0A001-18EB-499D-B266-2F2910744274")]
@@ -59,7 +90,7 @@ pub enum CowboyCharacteristic {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyLockCmd {
     /// Lock the bike.
     ///
@@ -71,27 +102,27 @@ pub enum CowboyLockCmd {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyDashboardCmd {
     Read,
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyDfcCmd {
     /// Read the fitness information of the bike starting from the given offset.
     Read(u32),
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CowboyTripCmd {
     /// Read the current trip information of the bike.
     Read,
 }
 
 #[non_exhaustive]
-#[derive(Command, Debug, Clone, Copy)]
+#[derive(Command, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SettingsWriteCmd {
     /// Turn the light on or off.
     ///
@@ -107,7 +138,10 @@ pub enum SettingsWriteCmd {
     #[mode(write)]
     ReadAutoLock,
 
-    /// Set the maximum assisted speed in km/h.
+    /// Set the maximum assisted speed.
+    ///
+    /// The bike stores the speed in km/h, so other units are converted
+    /// before encoding.
     #[mode(write_with_response)]
     SetMaxAssistedSpeed(Speed),
 
@@ -152,6 +186,9 @@ pub enum SettingsWriteCmd {
     ReadTorqueGain,
 
     /// Read the content of the given register.
+    ///
+    /// Any address is accepted: [`SettingsWriteCmd::read_register`] refuses
+    /// the ones known to be unsafe.
     #[mode(write)]
     ReadRegister(u16),
 
@@ -165,6 +202,21 @@ pub enum SettingsWriteCmd {
     #[mode(write)]
     ReadMotorTorqueMode,
 
+    /// Write a value to any register, for registers without a typed command.
+    ///
+    /// This is meant for experiments: nothing checks that the register
+    /// exists, or that the value is safe for it.
+    #[mode(write_with_response)]
+    RawWrite {
+        device: u8,
+        register: u16,
+        value: u16,
+    },
+
+    /// Read any register, for registers without a typed command.
+    #[mode(write)]
+    RawRead { device: u8, register: u16 },
+
     /// Write all the modified settings to flash memory of the bike.
     ///
     /// This will make the settings persistent if the is locked, or the battery
@@ -178,9 +230,277 @@ pub enum SettingsWriteCmd {
 }
 
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SettingsReadCmd {}
 
+/// The category of a settings command, for grouping them in a UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsCategory {
+    /// Reads the current value of a setting.
+    Read,
+
+    /// Modifies a setting.
+    Write,
+
+    /// Controls the flash memory the settings are persisted to.
+    Flash,
+}
+
+impl CowboyService {
+    /// Get the write mode for a given command.
+    ///
+    /// Commands that don't declare a mode (e.g. reads, or the commands of
+    /// the Cowboy characteristic) default to [`CmdMode::WriteOnly`].
+    pub fn mode(&self) -> CmdMode {
+        match self {
+            Self::Settings(characteristic) => characteristic.mode(),
+            Self::Cowboy(characteristic) => characteristic.mode(),
+        }
+    }
+
+    /// Whether the characteristic delivers its data through notifications.
+    ///
+    /// Notifications must be enabled by writing [`cccd_enable_notify`] to
+    /// the characteristic's [`DESCRIPTOR_CLIENT_CHARACTERISTIC_CONFIGURATION`]
+    /// before the data can be received.
+    pub fn requires_notify(&self) -> bool {
+        match self {
+            Self::Settings(characteristic) => characteristic.requires_notify(),
+            Self::Cowboy(characteristic) => characteristic.requires_notify(),
+        }
+    }
+
+    /// Check that every value carried by the command is in range.
+    ///
+    /// Returns the first invalid value found.
+    pub fn validate(&self) -> Result<(), FieldError> {
+        match self {
+            Self::Settings(SettingsCharacteristic::Write(cmd)) => cmd.validate(),
+            Self::Settings(SettingsCharacteristic::Read(_)) | Self::Cowboy(_) => Ok(()),
+        }
+    }
+}
+
+impl SettingsCharacteristic {
+    /// Get the write mode for a given command.
+    ///
+    /// Read commands default to [`CmdMode::WriteOnly`].
+    pub fn mode(&self) -> CmdMode {
+        match self {
+            Self::Write(cmd) => cmd.mode(),
+            Self::Read(_) => CmdMode::WriteOnly,
+        }
+    }
+
+    /// Whether the characteristic delivers its data through notifications.
+    ///
+    /// Responses to the settings commands are notified on the read
+    /// characteristic.
+    pub fn requires_notify(&self) -> bool {
+        match self {
+            Self::Write(_) => false,
+            Self::Read(_) => true,
+        }
+    }
+}
+
+impl CowboyCharacteristic {
+    /// Get the write mode for a given command.
+    ///
+    /// None of these commands declare a mode, so they all default to
+    /// [`CmdMode::WriteOnly`].
+    pub fn mode(&self) -> CmdMode {
+        match self {
+            Self::Lock(_)
+            | Self::Dashboard(_)
+            | Self::DataFitnessCollectorRequest(_)
+            | Self::Trip(_) => CmdMode::WriteOnly,
+        }
+    }
+
+    /// Whether the characteristic delivers its data through notifications.
+    pub fn requires_notify(&self) -> bool {
+        match self {
+            Self::Lock(_) => false,
+            Self::Dashboard(_) | Self::DataFitnessCollectorRequest(_) | Self::Trip(_) => true,
+        }
+    }
+}
+
+/// Every command reading a known setting of the bike.
+const ALL_READS: [SettingsWriteCmd; 6] = [
+    SettingsWriteCmd::ReadAutoLock,
+    SettingsWriteCmd::ReadMaxAssistedSpeed,
+    SettingsWriteCmd::ReadFieldWeakening,
+    SettingsWriteCmd::ReadHallInterpolation,
+    SettingsWriteCmd::ReadTorqueGain,
+    SettingsWriteCmd::ReadMotorTorqueMode,
+];
+
+impl SettingsWriteCmd {
+    /// Get the category of the command.
+    pub fn category(&self) -> SettingsCategory {
+        use SettingsWriteCmd::*;
+
+        match self {
+            ReadAutoLock
+            | ReadMaxAssistedSpeed
+            | ReadFieldWeakening
+            | ReadHallInterpolation
+            | ReadTorqueGain
+            | ReadRegister(_)
+            | ReadMotorTorqueMode
+            | RawRead { .. } => SettingsCategory::Read,
+            SetLight(_)
+            | SetAutoLock(_)
+            | SetMaxAssistedSpeed(_)
+            | SetFieldWeakening(_)
+            | SetHallInterpolation(_)
+            | SetTorqueGain(_)
+            | SetMotorTorqueMode(_)
+            | RawWrite { .. } => SettingsCategory::Write,
+            WriteFlash | CloseFlash => SettingsCategory::Flash,
+        }
+    }
+
+    /// Check that the value carried by the command is in range.
+    ///
+    /// Most values can only be built in range, but [`Speed`] has public
+    /// fields and may hold any value.
+    pub fn validate(&self) -> Result<(), FieldError> {
+        use SettingsWriteCmd::*;
+
+        match self {
+            SetMaxAssistedSpeed(v) => Speed::new(v.value, v.unit).map(|_| ()),
+            SetFieldWeakening(v) => FieldWeakening::new(v.value()).map(|_| ()),
+            SetHallInterpolation(v) => HallInterpolation::new(v.value()).map(|_| ()),
+            SetTorqueGain(v) => TorqueGain::new(v.value()).map(|_| ()),
+            SetLight(_)
+            | SetAutoLock(_)
+            | ReadAutoLock
+            | ReadMaxAssistedSpeed
+            | ReadFieldWeakening
+            | ReadHallInterpolation
+            | ReadTorqueGain
+            | ReadRegister(_)
+            | SetMotorTorqueMode(_)
+            | ReadMotorTorqueMode
+            | RawWrite { .. }
+            | RawRead { .. }
+            | WriteFlash
+            | CloseFlash => Ok(()),
+        }
+    }
+
+    /// Creates a `ReadRegister` command, refusing the addresses in
+    /// [`DENIED_READS`].
+    pub fn read_register(address: u16) -> Result<Self, FieldError> {
+        if DENIED_READS.contains(&address) {
+            return Err(FieldError::DeniedRegister { address });
+        }
+
+        Ok(Self::ReadRegister(address))
+    }
+
+    /// Get every command reading a known setting of the bike.
+    ///
+    /// `ReadRegister` reads an arbitrary register, so it isn't included.
+    pub fn all_reads() -> impl Iterator<Item = SettingsWriteCmd> {
+        ALL_READS.into_iter()
+    }
+
+    /// Get the register read by the command, as addressed in its header.
+    ///
+    /// `None` is returned for commands that aren't reads.
+    pub fn register(&self) -> Option<Register> {
+        if self.category() != SettingsCategory::Read {
+            return None;
+        }
+
+        let frame: [u8; 11] = (*self).try_into().ok()?;
+        Some(Register::of(&frame))
+    }
+
+    /// Get the frames of [`SettingsWriteCmd::all_reads`], in the same order.
+    pub fn read_all_frames() -> Result<[[u8; 11]; ALL_READS.len()], CmdError> {
+        let mut frames = [[0x0; 11]; ALL_READS.len()];
+        for (frame, cmd) in frames.iter_mut().zip(ALL_READS) {
+            *frame = cmd.try_into()?;
+        }
+
+        Ok(frames)
+    }
+
+    /// Get the value written by the command, without the framing.
+    ///
+    /// This is the big-endian register value carried in bytes 7-8 of the
+    /// frame. Read commands don't write a value, so an empty vec is returned.
+    #[cfg(feature = "heapless")]
+    pub fn value_bytes(&self) -> Result<heapless::Vec<u8, 4>, CmdError> {
+        if self.category() == SettingsCategory::Read {
+            return Ok(heapless::Vec::new());
+        }
+
+        let frame: [u8; 11] = (*self).try_into()?;
+        Ok(frame[7..9].iter().copied().collect())
+    }
+}
+
+/// Remove duplicate commands, keeping the first occurrence of each.
+///
+/// If there are more than `N` distinct commands, the first one that doesn't
+/// fit is returned as the error.
+#[cfg(feature = "heapless")]
+pub fn dedup_commands<const N: usize>(
+    cmds: &[SettingsWriteCmd]
+) -> Result<heapless::Vec<SettingsWriteCmd, N>, SettingsWriteCmd> {
+    let mut deduped = heapless::Vec::new();
+    for cmd in cmds {
+        if !deduped.contains(cmd) {
+            deduped.push(*cmd)?;
+        }
+    }
+
+    Ok(deduped)
+}
+
+impl TryFrom<CowboyService> for [u8; 11] {
+    type Error = CmdError;
+
+    fn try_from(service: CowboyService) -> Result<Self, Self::Error> {
+        match service {
+            CowboyService::Settings(characteristic) => characteristic.try_into(),
+            CowboyService::Cowboy(characteristic) => characteristic.try_into(),
+        }
+    }
+}
+
+impl TryFrom<SettingsCharacteristic> for [u8; 11] {
+    type Error = CmdError;
+
+    fn try_from(characteristic: SettingsCharacteristic) -> Result<Self, Self::Error> {
+        match characteristic {
+            SettingsCharacteristic::Write(cmd) => cmd.try_into(),
+            SettingsCharacteristic::Read(cmd) => cmd.try_into(),
+        }
+    }
+}
+
+impl TryFrom<CowboyCharacteristic> for [u8; 11] {
+    type Error = CmdError;
+
+    fn try_from(characteristic: CowboyCharacteristic) -> Result<Self, Self::Error> {
+        use CowboyCharacteristic::*;
+
+        match characteristic {
+            Lock(cmd) => cmd.try_into(),
+            Dashboard(cmd) => cmd.try_into(),
+            DataFitnessCollectorRequest(cmd) => cmd.try_into(),
+            Trip(cmd) => cmd.try_into(),
+        }
+    }
+}
+
 impl TryFrom<CowboyLockCmd> for [u8; 11] {
     type Error = CmdError;
 
@@ -194,6 +514,27 @@ impl TryFrom<CowboyLockCmd> for [u8; 11] {
     }
 }
 
+impl TryFrom<[u8; 11]> for CowboyLockCmd {
+    type Error = CmdError;
+
+    /// Decode a `SetLock` frame.
+    fn try_from(frame: [u8; 11]) -> Result<Self, Self::Error> {
+        if !verify_checksum(&frame) {
+            return Err(CmdError::BadChecksum);
+        }
+
+        match frame {
+            [v @ (0x0 | 0x1), 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, _, _] => {
+                Ok(CowboyLockCmd::SetLock(v == 0x1))
+            }
+            [0x0 | 0x1, ..] => Err(CmdError::UnexpectedHeader {
+                offset: frame[1..9].iter().position(|&b| b != 0x0).unwrap_or(0) + 1,
+            }),
+            _ => Err(CmdError::UnexpectedHeader { offset: 0 }),
+        }
+    }
+}
+
 impl TryFrom<CowboyDashboardCmd> for [u8; 11] {
     type Error = CmdError;
 
@@ -206,6 +547,24 @@ impl TryFrom<CowboyDashboardCmd> for [u8; 11] {
     }
 }
 
+impl CowboyDfcCmd {
+    /// Creates a read from `offset`, checking it is one of the `count`
+    /// records available.
+    ///
+    /// Reading past the last record doesn't fail on the bike, but returns
+    /// garbage.
+    pub fn read_checked(
+        offset: u32,
+        count: u32,
+    ) -> Result<Self, CmdError> {
+        if offset >= count {
+            return Err(CmdError::OffsetOutOfRange { offset, count });
+        }
+
+        Ok(Self::Read(offset))
+    }
+}
+
 impl TryFrom<CowboyDfcCmd> for [u8; 11] {
     type Error = CmdError;
 
@@ -215,7 +574,7 @@ impl TryFrom<CowboyDfcCmd> for [u8; 11] {
         Ok(match cmd {
             Read(offset) => {
                 let mut base = [0x0; 11];
-                base.copy_from_slice(&offset.to_le_bytes());
+                base[..4].copy_from_slice(&offset.to_le_bytes());
                 base
             }
         })
@@ -247,78 +606,223 @@ impl TryFrom<SettingsWriteCmd> for [u8; 11] {
 
     fn try_from(cmd: SettingsWriteCmd) -> Result<Self, Self::Error> {
         use SettingsWriteCmd::*;
+
+        // Keep this match free of wildcard arms, so adding a variant without
+        // encoding it is a compile error.
         Ok(match cmd {
-            SetLight(v) => {
-                cmd!([0xA, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, v as u8])
-            }
-            SetMaxAssistedSpeed(Speed { value: v, .. }) => {
-                cmd!([0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x1E], v as u16)
-            }
-            ReadMaxAssistedSpeed => {
-                cmd!([0xA, 0x3, 0x0, 0x4, 0x0, 0x1, 0x0, 0x0, 0x0])
-            }
-            SetAutoLock(v) => {
-                cmd!([0xA, 0x10, 0x0, 0x0, 0x0, 0x1, 0x2, 0x0, 0x0], v as u16)
+            SetLight(v) => cmd!(write_body(0xA, LIGHT), v as u16),
+            SetMaxAssistedSpeed(v) => {
+                cmd!(write_body(0xA, MAX_ASSISTED_SPEED), v.to_kmh().value as u16)
             }
-            ReadAutoLock => cmd!([0xA, 0x3, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0]),
+            ReadMaxAssistedSpeed => cmd!(read_body(0xA, MAX_ASSISTED_SPEED)),
+            SetAutoLock(v) => cmd!(write_body(0xA, AUTO_LOCK), v as u16),
+            ReadAutoLock => cmd!(read_body(0xA, AUTO_LOCK)),
             SetFieldWeakening(v) => {
-                cmd!(
-                    [0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0],
-                    v.weakening().into()
-                )
-            }
-            ReadFieldWeakening => {
-                cmd!([0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x0, 0x0, 0x0])
-            }
-            ReadHallInterpolation => {
-                cmd!([0x1, 0x3, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x0])
+                cmd!(write_body(0x1, FIELD_WEAKENING), v.percent().into())
             }
+            ReadFieldWeakening => cmd!(read_body(0x1, FIELD_WEAKENING)),
+            ReadHallInterpolation => cmd!(read_body(0x1, HALL_INTERPOLATION)),
             SetHallInterpolation(v) => {
                 cmd!(
-                    [0x1, 0x10, 0x0, 0x80, 0x0, 0x1, 0x2, 0x0, 0x0],
+                    write_body(0x1, HALL_INTERPOLATION),
                     v.interpolation().into()
                 )
             }
-            ReadTorqueGain => {
-                cmd!([0x1, 0x3, 0x0, 0xB3, 0x0, 0x1, 0x0, 0x0, 0x0])
+            ReadTorqueGain => cmd!(read_body(0x1, TORQUE_GAIN)),
+            SetTorqueGain(v) => cmd!(write_body(0x1, TORQUE_GAIN), v.value().into()),
+            // The register to read is carried in the value.
+            ReadRegister(v) => cmd!(read_body(0x1, 0x0), v),
+            ReadMotorTorqueMode => cmd!(read_body(0x1, TORQUE_MODE)),
+            SetMotorTorqueMode(TorqueMode { speed_limit }) => {
+                cmd!(write_body(0x1, TORQUE_MODE), speed_limit as u16 + 1)
             }
-            SetTorqueGain(v) => {
-                cmd!(
-                    [0x1, 0x10, 0x0, 0xB3, 0x0, 0x1, 0x2, 0x0, 0x0],
-                    v.gain.into()
-                )
+            RawWrite {
+                device,
+                register,
+                value,
+            } => cmd!(write_body(device, register), value),
+            RawRead { device, register } => cmd!(read_body(device, register)),
+            WriteFlash => cmd!(write_body(0x1, FLASH), 0x7FFF),
+            CloseFlash => cmd!(write_body(0x1, FLASH), 0x0),
+        })
+    }
+}
+
+/// Build the body of a read (function `0x03`) of one register.
+fn read_body(
+    device: u8,
+    register: u16,
+) -> [u8; 9] {
+    let [reg_hi, reg_lo] = register.to_be_bytes();
+    [device, 0x3, reg_hi, reg_lo, 0x0, 0x1, 0x0, 0x0, 0x0]
+}
+
+/// Build the body of a write (function `0x10`) of one register, with its
+/// value left empty.
+fn write_body(
+    device: u8,
+    register: u16,
+) -> [u8; 9] {
+    let [reg_hi, reg_lo] = register.to_be_bytes();
+    [device, 0x10, reg_hi, reg_lo, 0x0, 0x1, 0x2, 0x0, 0x0]
+}
+
+impl TryFrom<Speed> for [u8; 11] {
+    type Error = CmdError;
+
+    /// Build the [`SettingsWriteCmd::SetMaxAssistedSpeed`] frame.
+    fn try_from(value: Speed) -> Result<Self, Self::Error> {
+        SettingsWriteCmd::SetMaxAssistedSpeed(value).try_into()
+    }
+}
+
+impl TryFrom<FieldWeakening> for [u8; 11] {
+    type Error = CmdError;
+
+    /// Build the [`SettingsWriteCmd::SetFieldWeakening`] frame.
+    fn try_from(value: FieldWeakening) -> Result<Self, Self::Error> {
+        SettingsWriteCmd::SetFieldWeakening(value).try_into()
+    }
+}
+
+impl TryFrom<HallInterpolation> for [u8; 11] {
+    type Error = CmdError;
+
+    /// Build the [`SettingsWriteCmd::SetHallInterpolation`] frame.
+    fn try_from(value: HallInterpolation) -> Result<Self, Self::Error> {
+        SettingsWriteCmd::SetHallInterpolation(value).try_into()
+    }
+}
+
+impl TryFrom<TorqueGain> for [u8; 11] {
+    type Error = CmdError;
+
+    /// Build the [`SettingsWriteCmd::SetTorqueGain`] frame.
+    fn try_from(value: TorqueGain) -> Result<Self, Self::Error> {
+        SettingsWriteCmd::SetTorqueGain(value).try_into()
+    }
+}
+
+impl TryFrom<TorqueMode> for [u8; 11] {
+    type Error = CmdError;
+
+    /// Build the [`SettingsWriteCmd::SetMotorTorqueMode`] frame.
+    fn try_from(value: TorqueMode) -> Result<Self, Self::Error> {
+        SettingsWriteCmd::SetMotorTorqueMode(value).try_into()
+    }
+}
+
+impl TryFrom<[u8; 11]> for SettingsWriteCmd {
+    type Error = CmdError;
+
+    /// Decode a settings frame, as sent by the app.
+    ///
+    /// The command is identified from the device, function code and register
+    /// of the frame. Frames that don't match exactly one command are rejected
+    /// with [`CmdError::UnexpectedHeader`]. Frames built with `RawWrite` or
+    /// `RawRead` decode to the typed command of their register, if any.
+    fn try_from(frame: [u8; 11]) -> Result<Self, Self::Error> {
+        use SettingsWriteCmd::*;
+
+        if !verify_checksum(&frame) {
+            return Err(CmdError::BadChecksum);
+        }
+
+        let [device, function, reg_hi, reg_lo, 0x0, 0x1, bytes, val_hi, val_lo, _, _] = frame
+        else {
+            return Err(CmdError::UnexpectedHeader {
+                offset: unexpected_offset(&frame),
+            });
+        };
+        let register = u16::from_be_bytes([reg_hi, reg_lo]);
+        let value = u16::from_be_bytes([val_hi, val_lo]);
+
+        Ok(match (device, function, register, bytes, value) {
+            (0xA, 0x10, LIGHT, 0x2, v) => SetLight(value_bool(v)?),
+            (0xA, 0x10, AUTO_LOCK, 0x2, v) => SetAutoLock(value_bool(v)?),
+            (0xA, 0x3, AUTO_LOCK, 0x0, 0x0) => ReadAutoLock,
+            (0xA, 0x10, MAX_ASSISTED_SPEED, 0x2, v) => {
+                SetMaxAssistedSpeed(Speed::kmh(value_u8(v)?)?)
             }
-            ReadRegister(v) => {
-                cmd!([0x1, 0x3, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0], v)
+            (0xA, 0x3, MAX_ASSISTED_SPEED, 0x0, 0x0) => ReadMaxAssistedSpeed,
+            (0x1, 0x10, FIELD_WEAKENING, 0x2, v) => {
+                SetFieldWeakening(FieldWeakening::new(value_u8(v)?)?)
             }
-            ReadMotorTorqueMode => {
-                cmd!([0x1, 0x3, 0x0, 0xB, 0x0, 0x1, 0x0, 0x0, 0x0])
+            (0x1, 0x3, FIELD_WEAKENING, 0x0, 0x0) => ReadFieldWeakening,
+            (0x1, 0x10, HALL_INTERPOLATION, 0x2, v) => {
+                SetHallInterpolation(HallInterpolation::new(value_u8(v)?)?)
             }
-            SetMotorTorqueMode(TorqueMode { speed_limit }) => {
-                cmd!([
-                    0x1,
-                    0x10,
-                    0x0,
-                    0xB,
-                    0x0,
-                    0x1,
-                    0x2,
-                    0x0,
-                    speed_limit as u8 + 1
-                ])
+            (0x1, 0x3, HALL_INTERPOLATION, 0x0, 0x0) => ReadHallInterpolation,
+            (0x1, 0x10, TORQUE_GAIN, 0x2, v) => SetTorqueGain(TorqueGain::new(value_u8(v)?)?),
+            (0x1, 0x3, TORQUE_GAIN, 0x0, 0x0) => ReadTorqueGain,
+            (0x1, 0x3, 0x0, 0x0, v) => ReadRegister(v),
+            (0x1, 0x10, TORQUE_MODE, 0x2, v) => SetMotorTorqueMode(value_u8(v)?.try_into()?),
+            (0x1, 0x3, TORQUE_MODE, 0x0, 0x0) => ReadMotorTorqueMode,
+            (0x1, 0x10, FLASH, 0x2, 0x7FFF) => WriteFlash,
+            (0x1, 0x10, FLASH, 0x2, 0x0) => CloseFlash,
+            _ => {
+                return Err(CmdError::UnexpectedHeader {
+                    offset: unexpected_offset(&frame),
+                })
             }
-            WriteFlash => cmd!([0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x7F, 0xFF]),
-            CloseFlash => cmd!([0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x0, 0x0]),
         })
     }
 }
 
+/// Get the offset of the first byte of a settings frame no command accepts.
+///
+/// The frame is compared against the header of every known command, and
+/// fails where the longest matching header ends. A frame whose header is
+/// known fails at its value, at offset 7.
+fn unexpected_offset(frame: &[u8; 11]) -> usize {
+    let headers = [
+        write_body(0xA, LIGHT),
+        write_body(0xA, AUTO_LOCK),
+        read_body(0xA, AUTO_LOCK),
+        write_body(0xA, MAX_ASSISTED_SPEED),
+        read_body(0xA, MAX_ASSISTED_SPEED),
+        write_body(0x1, FIELD_WEAKENING),
+        read_body(0x1, FIELD_WEAKENING),
+        write_body(0x1, HALL_INTERPOLATION),
+        read_body(0x1, HALL_INTERPOLATION),
+        write_body(0x1, TORQUE_GAIN),
+        read_body(0x1, TORQUE_GAIN),
+        read_body(0x1, 0x0),
+        write_body(0x1, TORQUE_MODE),
+        read_body(0x1, TORQUE_MODE),
+        write_body(0x1, FLASH),
+    ];
+
+    headers
+        .iter()
+        .map(|header| {
+            header[..7]
+                .iter()
+                .zip(frame)
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Decode a register value holding a flag.
+fn value_bool(value: u16) -> Result<bool, FieldError> {
+    match value_u8(value)? {
+        0x0 => Ok(false),
+        0x1 => Ok(true),
+        got => Err(FieldError::InvalidVariant { got }),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::assert_all;
     use crate::che;
     use crate::chk;
+    use crate::roundtrip;
+    use crate::types::SpeedUnit;
 
     #[test]
     fn test_cmd_checksum_is_valid() {
@@ -336,7 +840,7 @@ mod test {
             (chk!(ReadHallInterpolation), [89, 73]),
             (chk!(SetHallInterpolation(Default::default())), [185, 144]),
             (chk!(ReadTorqueGain), [92, 138]),
-            (chk!(SetTorqueGain(Default::default())), [188, 83]),
+            (chk!(SetTorqueGain(TorqueGain::new(0).unwrap())), [188, 83]),
             (chk!(ReadRegister(0)), [70, 137]),
             (chk!(ReadMotorTorqueMode), [71, 242]),
             (chk!(SetMotorTorqueMode(Default::default())), [102, 235]),
@@ -358,4 +862,638 @@ mod test {
             (che!(CowboyTripCmd::Read), StrictlyRead),
         ])
     }
+
+    #[test]
+    fn test_service_mode() {
+        use CmdMode::*;
+
+        let settings = |cmd| CowboyService::Settings(SettingsCharacteristic::Write(cmd));
+        let cowboy = CowboyService::Cowboy;
+
+        assert_all!([
+            (settings(SettingsWriteCmd::SetLight(true)).mode(), WriteOnly),
+            (
+                settings(SettingsWriteCmd::SetAutoLock(true)).mode(),
+                WriteWithResponse
+            ),
+            (settings(SettingsWriteCmd::ReadTorqueGain).mode(), WriteOnly),
+            (
+                settings(SettingsWriteCmd::SetTorqueGain(TorqueGain::new(0).unwrap())).mode(),
+                WriteWithResponse
+            ),
+            (
+                cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::SetLock(true))).mode(),
+                WriteOnly
+            ),
+            (
+                cowboy(CowboyCharacteristic::Dashboard(CowboyDashboardCmd::Read)).mode(),
+                WriteOnly
+            ),
+            (
+                cowboy(CowboyCharacteristic::Trip(CowboyTripCmd::Read)).mode(),
+                WriteOnly
+            ),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_value_bytes() {
+        use SettingsWriteCmd::*;
+
+        let value = |cmd: SettingsWriteCmd| cmd.value_bytes().unwrap();
+
+        assert_all!([
+            (value(SetLight(true)), [0x0, 0x1].as_slice()),
+            (value(SetAutoLock(false)), &[0x0, 0x0]),
+            (value(SetMaxAssistedSpeed(Default::default())), &[0x0, 0x19]),
+            (
+                value(SetFieldWeakening(FieldWeakening::new(20).unwrap())),
+                &[0x0, 0x14]
+            ),
+            (
+                value(SetHallInterpolation(HallInterpolation::new(5).unwrap())),
+                &[0x0, 0x5]
+            ),
+            (
+                value(SetTorqueGain(TorqueGain::new(0).unwrap())),
+                &[0x0, 0x0]
+            ),
+            (
+                value(SetMotorTorqueMode(TorqueMode { speed_limit: true })),
+                &[0x0, 0x2]
+            ),
+            (value(WriteFlash), &[0x7F, 0xFF]),
+            (value(CloseFlash), &[0x0, 0x0]),
+            (value(ReadAutoLock), &[]),
+            (value(ReadMaxAssistedSpeed), &[]),
+            (value(ReadFieldWeakening), &[]),
+            (value(ReadHallInterpolation), &[]),
+            (value(ReadTorqueGain), &[]),
+            (value(ReadRegister(0x4)), &[]),
+            (value(ReadMotorTorqueMode), &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_settings_write_cmd_bodies() {
+        use SettingsWriteCmd::*;
+
+        let body = |cmd: SettingsWriteCmd| {
+            let frame: [u8; 11] = cmd.try_into().unwrap();
+            <[u8; 9]>::try_from(&frame[..9]).unwrap()
+        };
+
+        assert_all!([
+            (
+                body(SetLight(true)),
+                [0xA, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x1]
+            ),
+            (
+                body(SetAutoLock(true)),
+                [0xA, 0x10, 0x0, 0x0, 0x0, 0x1, 0x2, 0x0, 0x1]
+            ),
+            (
+                body(ReadAutoLock),
+                [0xA, 0x3, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(SetMaxAssistedSpeed(Speed::kmh(25).unwrap())),
+                [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19]
+            ),
+            (
+                body(ReadMaxAssistedSpeed),
+                [0xA, 0x3, 0x0, 0x4, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(SetFieldWeakening(FieldWeakening::new(25).unwrap())),
+                [0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x19]
+            ),
+            (
+                body(ReadFieldWeakening),
+                [0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(SetHallInterpolation(HallInterpolation::new(5).unwrap())),
+                [0x1, 0x10, 0x0, 0x80, 0x0, 0x1, 0x2, 0x0, 0x5]
+            ),
+            (
+                body(ReadHallInterpolation),
+                [0x1, 0x3, 0x0, 0x80, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(SetTorqueGain(TorqueGain::new(30).unwrap())),
+                [0x1, 0x10, 0x0, 0xB3, 0x0, 0x1, 0x2, 0x0, 0x1E]
+            ),
+            (
+                body(ReadTorqueGain),
+                [0x1, 0x3, 0x0, 0xB3, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(ReadRegister(0x1234)),
+                [0x1, 0x3, 0x0, 0x0, 0x0, 0x1, 0x0, 0x12, 0x34]
+            ),
+            (
+                body(SetMotorTorqueMode(TorqueMode { speed_limit: true })),
+                [0x1, 0x10, 0x0, 0xB, 0x0, 0x1, 0x2, 0x0, 0x2]
+            ),
+            (
+                body(ReadMotorTorqueMode),
+                [0x1, 0x3, 0x0, 0xB, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(RawWrite {
+                    device: 0xA,
+                    register: 0x1234,
+                    value: 0xABCD
+                }),
+                [0xA, 0x10, 0x12, 0x34, 0x0, 0x1, 0x2, 0xAB, 0xCD]
+            ),
+            (
+                body(RawRead {
+                    device: 0xA,
+                    register: 0x1234
+                }),
+                [0xA, 0x3, 0x12, 0x34, 0x0, 0x1, 0x0, 0x0, 0x0]
+            ),
+            (
+                body(WriteFlash),
+                [0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x7F, 0xFF]
+            ),
+            (
+                body(CloseFlash),
+                [0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x0, 0x0]
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_setting_frames_match_cmd_frames() {
+        use SettingsWriteCmd::*;
+
+        let speed = Speed::default();
+        let weakening = FieldWeakening::new(20).unwrap();
+        let interpolation = HallInterpolation::new(5).unwrap();
+        let gain = TorqueGain::new(0).unwrap();
+        let mode = TorqueMode { speed_limit: true };
+
+        assert_all!([
+            (speed.try_into(), SetMaxAssistedSpeed(speed).try_into()),
+            (
+                weakening.try_into(),
+                SetFieldWeakening(weakening).try_into()
+            ),
+            (
+                interpolation.try_into(),
+                SetHallInterpolation(interpolation).try_into()
+            ),
+            (gain.try_into(), SetTorqueGain(gain).try_into()),
+            (mode.try_into(), SetMotorTorqueMode(mode).try_into()),
+        ] as [(Result<[u8; 11], CmdError>, _); 5]);
+    }
+
+    #[test]
+    fn test_cccd() {
+        assert_eq!(cccd_enable_notify(), [0x01, 0x00]);
+        assert_eq!(cccd_disable(), [0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_requires_notify() {
+        use CowboyCharacteristic::*;
+
+        assert_all!([
+            (Lock(CowboyLockCmd::ReadLock).requires_notify(), false),
+            (Dashboard(CowboyDashboardCmd::Read).requires_notify(), true),
+            (
+                DataFitnessCollectorRequest(CowboyDfcCmd::Read(0)).requires_notify(),
+                true
+            ),
+            (Trip(CowboyTripCmd::Read).requires_notify(), true),
+        ]);
+
+        let light = SettingsCharacteristic::Write(SettingsWriteCmd::SetLight(true));
+        assert!(!light.requires_notify());
+        assert!(!CowboyService::Settings(light).requires_notify());
+        assert!(CowboyService::Cowboy(Trip(CowboyTripCmd::Read)).requires_notify());
+    }
+
+    #[test]
+    fn test_settings_category() {
+        use SettingsCategory::*;
+        use SettingsWriteCmd::*;
+
+        assert_all!([
+            (SetLight(true).category(), Write),
+            (SetAutoLock(true).category(), Write),
+            (ReadAutoLock.category(), Read),
+            (SetMaxAssistedSpeed(Default::default()).category(), Write),
+            (ReadMaxAssistedSpeed.category(), Read),
+            (SetFieldWeakening(Default::default()).category(), Write),
+            (ReadFieldWeakening.category(), Read),
+            (SetHallInterpolation(Default::default()).category(), Write),
+            (ReadHallInterpolation.category(), Read),
+            (SetTorqueGain(TorqueGain::new(0).unwrap()).category(), Write),
+            (ReadTorqueGain.category(), Read),
+            (ReadRegister(0).category(), Read),
+            (SetMotorTorqueMode(Default::default()).category(), Write),
+            (ReadMotorTorqueMode.category(), Read),
+            (WriteFlash.category(), Flash),
+            (CloseFlash.category(), Flash),
+        ]);
+    }
+
+    #[test]
+    fn test_decode_lock_cmd() {
+        use CowboyLockCmd::*;
+
+        for lock in [true, false] {
+            let frame: [u8; 11] = SetLock(lock).try_into().unwrap();
+            let SetLock(decoded) = CowboyLockCmd::try_from(frame).unwrap() else {
+                panic!("expected SetLock");
+            };
+            assert_eq!(decoded, lock);
+        }
+
+        let mut frame: [u8; 11] = SetLock(true).try_into().unwrap();
+        frame[10] ^= 0xFF;
+        assert_eq!(
+            CowboyLockCmd::try_from(frame).err(),
+            Some(CmdError::BadChecksum)
+        );
+
+        let frame: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+        assert_eq!(
+            CowboyLockCmd::try_from(frame).err(),
+            Some(CmdError::UnexpectedHeader { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_decode_settings_write_cmd() {
+        use SettingsWriteCmd::*;
+
+        for cmd in [
+            SetLight(true),
+            SetLight(false),
+            SetAutoLock(true),
+            ReadAutoLock,
+            SetMaxAssistedSpeed(Default::default()),
+            ReadMaxAssistedSpeed,
+            SetFieldWeakening(FieldWeakening::new(20).unwrap()),
+            ReadFieldWeakening,
+            SetHallInterpolation(HallInterpolation::new(5).unwrap()),
+            ReadHallInterpolation,
+            SetTorqueGain(TorqueGain::new(12).unwrap()),
+            ReadTorqueGain,
+            ReadRegister(0x1234),
+            SetMotorTorqueMode(TorqueMode { speed_limit: true }),
+            SetMotorTorqueMode(TorqueMode { speed_limit: false }),
+            ReadMotorTorqueMode,
+            WriteFlash,
+            CloseFlash,
+        ] {
+            let frame: [u8; 11] = cmd.try_into().unwrap();
+            let decoded = SettingsWriteCmd::try_from(frame).unwrap();
+            assert_eq!(<[u8; 11]>::try_from(decoded), Ok(frame), "{cmd:?}");
+        }
+    }
+
+    #[test]
+    fn test_setting_values_roundtrip() {
+        use SettingsWriteCmd::*;
+
+        for value in [0, 20, 100] {
+            roundtrip!(SetFieldWeakening, FieldWeakening::new(value).unwrap());
+        }
+        for value in [0, 5, 25] {
+            roundtrip!(SetHallInterpolation, HallInterpolation::new(value).unwrap());
+        }
+        for value in [0, 12, TorqueGain::MAX] {
+            roundtrip!(SetTorqueGain, TorqueGain::new(value).unwrap());
+        }
+        for speed_limit in [true, false] {
+            roundtrip!(SetMotorTorqueMode, TorqueMode { speed_limit });
+        }
+        for value in [0, 25, 60] {
+            roundtrip!(SetMaxAssistedSpeed, Speed::kmh(value).unwrap());
+        }
+
+        // Decoded in km/h, which compares equal to the speed in mph.
+        roundtrip!(SetMaxAssistedSpeed, Speed::mph(15).unwrap());
+    }
+
+    #[test]
+    fn test_dfc_read() {
+        let frame = <[u8; 11]>::try_from(CowboyDfcCmd::Read(0x0102_0304));
+        assert_eq!(
+            frame,
+            Ok([0x4, 0x3, 0x2, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0])
+        );
+
+        assert_eq!(CowboyDfcCmd::read_checked(0, 10), Ok(CowboyDfcCmd::Read(0)));
+        assert_eq!(CowboyDfcCmd::read_checked(9, 10), Ok(CowboyDfcCmd::Read(9)));
+        assert_eq!(
+            CowboyDfcCmd::read_checked(10, 10),
+            Err(CmdError::OffsetOutOfRange {
+                offset: 10,
+                count: 10
+            })
+        );
+        assert_eq!(
+            CowboyDfcCmd::read_checked(0, 0),
+            Err(CmdError::OffsetOutOfRange {
+                offset: 0,
+                count: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_settings_write_cmd_errors() {
+        let decode = |body| SettingsWriteCmd::try_from(cmd!(body)).err();
+
+        assert_all!([
+            (
+                decode([0xA, 0x10, 0x0, 0x5, 0x0, 0x1, 0x2, 0x0, 0x0]),
+                Some(CmdError::UnexpectedHeader { offset: 3 })
+            ),
+            (
+                decode([0x2, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x1]),
+                Some(CmdError::UnexpectedHeader { offset: 0 })
+            ),
+            (
+                decode([0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x0, 0x1]),
+                Some(CmdError::UnexpectedHeader { offset: 7 })
+            ),
+            (
+                decode([0xA, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x2]),
+                Some(CmdError::InvalidField(FieldError::InvalidVariant {
+                    got: 0x2
+                }))
+            ),
+            (
+                decode([0x1, 0x10, 0x0, 0xB3, 0x0, 0x1, 0x2, 0x0, 0x2E]),
+                Some(CmdError::InvalidField(FieldError::InvalidRange {
+                    start: 0,
+                    end: 45,
+                    field: Some("torque_gain"),
+                }))
+            ),
+            (
+                decode([0x1, 0x10, 0x0, 0x80, 0x0, 0x1, 0x2, 0x0, 0x1A]),
+                Some(CmdError::InvalidField(FieldError::InvalidRange {
+                    start: 0,
+                    end: 0x19,
+                    field: Some("hall_interpolation"),
+                }))
+            ),
+            (
+                decode([0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x3D]),
+                Some(CmdError::InvalidField(FieldError::InvalidRange {
+                    start: 0,
+                    end: 0x3C,
+                    field: Some("max_assisted_speed"),
+                }))
+            ),
+        ]);
+
+        let mut frame: [u8; 11] = SettingsWriteCmd::WriteFlash.try_into().unwrap();
+        frame[9] ^= 0xFF;
+        assert_eq!(
+            SettingsWriteCmd::try_from(frame).err(),
+            Some(CmdError::BadChecksum)
+        );
+    }
+
+    #[test]
+    fn test_read_register() {
+        use SettingsWriteCmd::*;
+
+        assert_all!([
+            (SettingsWriteCmd::read_register(0x0), Ok(ReadRegister(0x0))),
+            (
+                SettingsWriteCmd::read_register(FIELD_WEAKENING),
+                Ok(ReadRegister(FIELD_WEAKENING))
+            ),
+            (
+                SettingsWriteCmd::read_register(0xFFFF),
+                Ok(ReadRegister(0xFFFF))
+            ),
+            (
+                SettingsWriteCmd::read_register(FLASH),
+                Err(FieldError::DeniedRegister { address: FLASH })
+            ),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_all_reads() {
+        use SettingsWriteCmd::*;
+
+        let frames = |cmds: &[SettingsWriteCmd]| {
+            cmds.iter()
+                .map(|cmd| <[u8; 11]>::try_from(*cmd).unwrap())
+                .collect::<heapless::Vec<_, 8>>()
+        };
+
+        let reads = SettingsWriteCmd::all_reads().collect::<heapless::Vec<_, 8>>();
+        assert_eq!(reads.len(), 6);
+        assert!(reads
+            .iter()
+            .all(|cmd| cmd.category() == SettingsCategory::Read));
+        assert_eq!(
+            frames(&reads),
+            frames(&[
+                ReadAutoLock,
+                ReadMaxAssistedSpeed,
+                ReadFieldWeakening,
+                ReadHallInterpolation,
+                ReadTorqueGain,
+                ReadMotorTorqueMode,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = CowboyService::from(SettingsWriteCmd::SetMaxAssistedSpeed(Speed {
+            value: 25,
+            unit: SpeedUnit::Kmh,
+        }));
+        assert_eq!(valid.validate(), Ok(()));
+
+        let invalid = CowboyService::from(SettingsWriteCmd::SetMaxAssistedSpeed(Speed {
+            value: 40,
+            unit: SpeedUnit::Mph,
+        }));
+        assert_eq!(
+            invalid.validate(),
+            Err(FieldError::InvalidRange {
+                start: 0,
+                end: 37,
+                field: Some("max_assisted_speed"),
+            })
+        );
+
+        let lock = CowboyService::Cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::SetLock(true)));
+        assert_eq!(lock.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_read_all_frames() {
+        let frames = SettingsWriteCmd::read_all_frames().unwrap();
+
+        for (frame, cmd) in frames.iter().zip(SettingsWriteCmd::all_reads()) {
+            assert!(verify_checksum(frame), "{cmd:?}");
+            assert_eq!(Ok(*frame), <[u8; 11]>::try_from(cmd));
+        }
+        assert_eq!(frames.len(), SettingsWriteCmd::all_reads().count());
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_dedup_commands() {
+        use SettingsWriteCmd::*;
+
+        let cmds = [
+            SetLight(true),
+            WriteFlash,
+            SetLight(true),
+            SetLight(false),
+            SetMaxAssistedSpeed(Speed::kmh(24).unwrap()),
+            SetMaxAssistedSpeed(Speed::mph(15).unwrap()),
+            WriteFlash,
+        ];
+
+        let deduped = dedup_commands::<8>(&cmds).unwrap();
+        assert_eq!(
+            deduped,
+            [
+                SetLight(true),
+                WriteFlash,
+                SetLight(false),
+                SetMaxAssistedSpeed(Speed::kmh(24).unwrap()),
+            ]
+        );
+
+        assert_eq!(dedup_commands::<2>(&cmds), Err(SetLight(false)));
+        assert_eq!(dedup_commands::<0>(&[]), Ok(heapless::Vec::new()));
+    }
+
+    #[test]
+    fn test_raw_commands() {
+        use SettingsWriteCmd::*;
+
+        let frame = |cmd: SettingsWriteCmd| <[u8; 11]>::try_from(cmd).unwrap();
+
+        assert_eq!(
+            frame(RawWrite {
+                device: 0x1,
+                register: FIELD_WEAKENING,
+                value: 20
+            }),
+            frame(SetFieldWeakening(FieldWeakening::new(20).unwrap()))
+        );
+        assert_eq!(
+            frame(RawWrite {
+                device: 0xA,
+                register: LIGHT,
+                value: 1
+            }),
+            frame(SetLight(true))
+        );
+        assert_eq!(
+            frame(RawRead {
+                device: 0x1,
+                register: TORQUE_GAIN
+            }),
+            frame(ReadTorqueGain)
+        );
+
+        let unknown = frame(RawWrite {
+            device: 0x1,
+            register: 0x42,
+            value: 0x1234,
+        });
+        assert!(verify_checksum(&unknown));
+        assert_eq!(
+            unknown[..9],
+            [0x1, 0x10, 0x0, 0x42, 0x0, 0x1, 0x2, 0x12, 0x34]
+        );
+        assert_eq!(
+            SettingsWriteCmd::try_from(unknown),
+            Err(CmdError::UnexpectedHeader { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn test_max_assisted_speed_is_encoded_in_kmh() {
+        let mph: [u8; 11] = SettingsWriteCmd::SetMaxAssistedSpeed(Speed {
+            value: 15,
+            unit: SpeedUnit::Mph,
+        })
+        .try_into()
+        .unwrap();
+        let kmh: [u8; 11] = SettingsWriteCmd::SetMaxAssistedSpeed(Speed {
+            value: 24,
+            unit: SpeedUnit::Kmh,
+        })
+        .try_into()
+        .unwrap();
+
+        // 15 mph is 24.14 km/h.
+        assert_eq!(mph[7..9], [0x0, 24]);
+        assert_eq!(mph, kmh);
+        assert!(verify_checksum(&mph));
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_modes_cover_all_variants() {
+        use core::fmt::Write;
+
+        use SettingsWriteCmd::*;
+
+        let cmds = [
+            SetLight(true),
+            SetAutoLock(true),
+            ReadAutoLock,
+            SetMaxAssistedSpeed(Default::default()),
+            ReadMaxAssistedSpeed,
+            SetFieldWeakening(Default::default()),
+            ReadFieldWeakening,
+            SetHallInterpolation(Default::default()),
+            ReadHallInterpolation,
+            SetTorqueGain(TorqueGain::new(0).unwrap()),
+            ReadTorqueGain,
+            ReadRegister(0x0),
+            SetMotorTorqueMode(Default::default()),
+            ReadMotorTorqueMode,
+            RawWrite {
+                device: 0x1,
+                register: 0x0,
+                value: 0x0,
+            },
+            RawRead {
+                device: 0x1,
+                register: 0x0,
+            },
+            WriteFlash,
+            CloseFlash,
+        ];
+
+        let modes = SettingsWriteCmd::modes();
+        assert_eq!(modes.len(), cmds.len());
+
+        let mut debug = heapless::String::<64>::new();
+        for (cmd, &(name, mode)) in cmds.iter().zip(modes) {
+            debug.clear();
+            write!(debug, "{cmd:?}").unwrap();
+
+            assert_eq!(debug.split(['(', ' ']).next(), Some(name));
+            assert_eq!(cmd.mode(), mode, "{name}");
+        }
+    }
 }
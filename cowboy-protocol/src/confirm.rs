@@ -0,0 +1,97 @@
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+use crate::types::FieldWeakening;
+
+/// A field weakening write followed by a read-back to confirm it.
+///
+/// Send both [`ConfirmedWrite::frames`], then hand the response to the read
+/// to [`ConfirmedWrite::verify_response`].
+#[derive(Clone, Copy, Debug)]
+pub struct ConfirmedWrite {
+    desired: FieldWeakening,
+}
+
+impl ConfirmedWrite {
+    /// Creates a confirmed write of the field weakening.
+    pub fn new(desired: FieldWeakening) -> Self {
+        Self { desired }
+    }
+
+    /// Returns the `SetFieldWeakening` frame followed by the
+    /// `ReadFieldWeakening` frame.
+    pub fn frames(&self) -> Result<[[u8; 11]; 2], CmdError> {
+        Ok([
+            SettingsWriteCmd::SetFieldWeakening(self.desired).try_into()?,
+            SettingsWriteCmd::ReadFieldWeakening.try_into()?,
+        ])
+    }
+
+    /// Checks that the response to the read-back matches the desired value.
+    ///
+    /// The response echoes the read header (function `0x03`, register
+    /// `0x81`) and carries the weakening as read from the bike in bytes 7-8.
+    /// Responses with an invalid checksum or for another register never match.
+    pub fn verify_response(
+        &self,
+        response: &[u8; 11],
+    ) -> bool {
+        FieldWeakening::from_response(response)
+            .is_ok_and(|weakening| weakening.percent() == self.desired.percent())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd;
+
+    fn response(
+        register: u8,
+        value: u16,
+    ) -> [u8; 11] {
+        cmd!([0x1, 0x3, 0x0, register, 0x0, 0x1, 0x2, 0x0, 0x0], value)
+    }
+
+    /// The response of a bike echoing the value of a write to its read-back.
+    fn echo(write: &[u8; 11]) -> [u8; 11] {
+        response(write[3], u16::from_be_bytes([write[7], write[8]]))
+    }
+
+    #[test]
+    fn test_confirmed_write_frames() {
+        let desired = FieldWeakening::new(20).unwrap();
+        let [write, read] = ConfirmedWrite::new(desired).frames().unwrap();
+
+        assert_eq!(
+            write,
+            <[u8; 11]>::try_from(SettingsWriteCmd::SetFieldWeakening(desired)).unwrap()
+        );
+        assert_eq!(
+            read,
+            <[u8; 11]>::try_from(SettingsWriteCmd::ReadFieldWeakening).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify_response() {
+        for percent in 0..=FieldWeakening::MAX {
+            let confirm = ConfirmedWrite::new(FieldWeakening::new(percent).unwrap());
+            let [write, _] = confirm.frames().unwrap();
+            assert!(confirm.verify_response(&echo(&write)), "{percent}%");
+        }
+
+        let confirm = ConfirmedWrite::new(FieldWeakening::new(20).unwrap());
+        let [write, _] = confirm.frames().unwrap();
+        let echoed = echo(&write);
+        assert_eq!(echoed[7..9], [0x0, 0x14]);
+
+        let mut other_register = write;
+        other_register[3] = 0x80;
+        assert!(!confirm.verify_response(&echo(&other_register)));
+        assert!(!confirm.verify_response(&response(0x81, 0x0)));
+
+        let mut corrupted = echoed;
+        corrupted[10] ^= 0xFF;
+        assert!(!confirm.verify_response(&corrupted));
+    }
+}
@@ -1,10 +1,127 @@
-#[derive(Clone, Copy, Debug)]
+use crate::mode::CmdMode;
+use crate::utils::BODY_LEN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FieldError {
-    InvalidRange { start: u8, end: u8 },
+    /// The value is outside `start..=end`.
+    ///
+    /// `field` names the setting the value is for, when known.
+    InvalidRange {
+        start: u8,
+        end: u8,
+        field: Option<&'static str>,
+    },
+
+    /// The byte doesn't map to any known variant.
+    InvalidVariant { got: u8 },
+
+    /// The string isn't a number, optionally followed by its unit.
+    InvalidFormat,
+
+    /// The string isn't a known unit.
+    UnknownUnit,
+
+    /// The register is denied to checked reads, see
+    /// [`crate::registers::DENIED_READS`].
+    DeniedRegister { address: u16 },
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CmdError {
     /// Cannot generate instruction for strictly read command.
     StrictlyRead,
+
+    /// The checksum of the frame doesn't match its content.
+    BadChecksum,
+
+    /// The frame doesn't match any known command, from the byte at `offset`.
+    UnexpectedHeader { offset: usize },
+
+    /// The frame matches a command, but carries an invalid value.
+    InvalidField(FieldError),
+
+    /// The value doesn't fit in the field it is written to.
+    ValueTooLarge { value: u32, max: u32 },
+
+    /// A field required to build the frame wasn't set.
+    MissingField { field: &'static str },
+
+    /// The offset is past the last of the `count` records available.
+    OffsetOutOfRange { offset: u32, count: u32 },
+
+    /// The flash command is emitted by the flash transaction itself.
+    FlashManaged,
+
+    /// The frame is to be sent with another write mode than its command's.
+    ModeMismatch { expected: CmdMode, got: CmdMode },
+}
+
+impl CmdError {
+    /// Get the offset, in the frame, of the byte decoding failed at.
+    ///
+    /// A bad checksum fails at the checksum. `None` is returned for errors
+    /// that aren't tied to a byte.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedHeader { offset } => Some(*offset),
+            Self::BadChecksum => Some(BODY_LEN),
+            Self::StrictlyRead
+            | Self::InvalidField(_)
+            | Self::ValueTooLarge { .. }
+            | Self::MissingField { .. }
+            | Self::OffsetOutOfRange { .. }
+            | Self::FlashManaged
+            | Self::ModeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<FieldError> for CmdError {
+    fn from(err: FieldError) -> Self {
+        Self::InvalidField(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd;
+    use crate::cmd::CowboyLockCmd;
+    use crate::cmd::SettingsWriteCmd;
+
+    #[test]
+    fn test_offset() {
+        let decode = |body| SettingsWriteCmd::try_from(cmd!(body)).unwrap_err();
+
+        // Unknown device.
+        let err = decode([0x2, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x1]);
+        assert_eq!(err.offset(), Some(0));
+
+        // Unknown function.
+        let err = decode([0xA, 0x6, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x1]);
+        assert_eq!(err.offset(), Some(1));
+
+        // Unknown register.
+        let err = decode([0xA, 0x10, 0x0, 0x5, 0x0, 0x1, 0x2, 0x0, 0x0]);
+        assert_eq!(err.offset(), Some(3));
+
+        // Read with a byte count.
+        let err = decode([0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0]);
+        assert_eq!(err.offset(), Some(6));
+
+        // Known header, but unknown flash value.
+        let err = decode([0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x0, 0x1]);
+        assert_eq!(err.offset(), Some(7));
+
+        let mut frame = cmd!([0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x0, 0x0, 0x0]);
+        frame[10] ^= 0xFF;
+        let err = SettingsWriteCmd::try_from(frame).unwrap_err();
+        assert_eq!((err, err.offset()), (CmdError::BadChecksum, Some(9)));
+
+        let lock = |body| CowboyLockCmd::try_from(cmd!(body)).unwrap_err().offset();
+        assert_eq!(lock([0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0]), Some(0));
+        assert_eq!(lock([0x1, 0x0, 0x0, 0x0, 0x0, 0x5, 0x0, 0x0, 0x0]), Some(5));
+
+        assert_eq!(CmdError::StrictlyRead.offset(), None);
+    }
 }
@@ -0,0 +1,397 @@
+use crate::cmd::SettingsCategory;
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+
+/// Frames persisting the settings written: `WriteFlash` once they are all
+/// written, then `CloseFlash`.
+pub(crate) const PERSIST: [SettingsWriteCmd; 2] =
+    [SettingsWriteCmd::WriteFlash, SettingsWriteCmd::CloseFlash];
+
+/// A set of writes persisted with `WriteFlash` and `CloseFlash`.
+///
+/// Forgetting to close the flash after writing to it can leave the bike in
+/// a bad state. Since `Drop` can't emit frames by itself, a transaction is
+/// driven through [`FlashTransaction::run`], which always emits the closing
+/// frame once the flash is written:
+///
+/// ```
+/// # use cowboy_protocol::error::CmdError;
+/// # use cowboy_protocol::flash::FlashTransaction;
+/// # use cowboy_protocol::SettingsWriteCmd;
+/// # let send = |_frame: [u8; 11]| Ok::<_, CmdError>(());
+/// FlashTransaction::run(send, |tx| {
+///     tx.write(SettingsWriteCmd::SetAutoLock(true))?;
+///     tx.write(SettingsWriteCmd::SetLight(false))
+/// })?;
+/// # Ok::<_, CmdError>(())
+/// ```
+pub struct FlashTransaction<'a, E> {
+    emit: &'a mut dyn FnMut([u8; 11]) -> Result<(), E>,
+}
+
+impl<E: From<CmdError>> FlashTransaction<'_, E> {
+    /// Run `writes` inside a flash transaction.
+    ///
+    /// Every frame is handed to `emit`: first the frames written by
+    /// `writes`, then `WriteFlash` and finally `CloseFlash`. If `writes`
+    /// fails, nothing is persisted: the frames it already emitted are
+    /// applied but not persisted, and nothing needs closing since the flash
+    /// was never written. Once `WriteFlash` is attempted, the closing frame
+    /// is emitted even if emitting it failed, in which case the first error
+    /// is returned.
+    pub fn run<F, W>(
+        mut emit: F,
+        writes: W,
+    ) -> Result<(), E>
+    where
+        F: FnMut([u8; 11]) -> Result<(), E>,
+        W: FnOnce(&mut FlashTransaction<'_, E>) -> Result<(), E>,
+    {
+        let [write_flash, close_flash] = PERSIST;
+
+        let mut tx = FlashTransaction { emit: &mut emit };
+        writes(&mut tx)?;
+        let result = tx.emit(write_flash);
+        let close = tx.emit(close_flash);
+
+        result.and(close)
+    }
+
+    /// Write a settings command as part of the transaction.
+    ///
+    /// Flash commands are refused with [`CmdError::FlashManaged`]: those are
+    /// emitted by [`FlashTransaction::run`].
+    pub fn write(
+        &mut self,
+        cmd: SettingsWriteCmd,
+    ) -> Result<(), E> {
+        if cmd.category() == SettingsCategory::Flash {
+            return Err(CmdError::FlashManaged.into());
+        }
+
+        self.emit(cmd)
+    }
+
+    fn emit(
+        &mut self,
+        cmd: SettingsWriteCmd,
+    ) -> Result<(), E> {
+        (self.emit)(cmd.try_into()?)
+    }
+}
+
+/// Commands waiting to be sent, flushed as frames in order.
+///
+/// The flash is managed by the queue: flushing sends `WriteFlash` and
+/// `CloseFlash` right after the last write, so the writes are persisted
+/// and the flash isn't left open.
+#[cfg(feature = "heapless")]
+#[derive(Clone, Debug, Default)]
+pub struct CommandQueue<const N: usize> {
+    cmds: heapless::Vec<SettingsWriteCmd, N>,
+}
+
+/// Iterator over the frames of a flushed [`CommandQueue`].
+///
+/// Drain it completely: stopping after the `WriteFlash` frame leaves the
+/// flash open.
+#[cfg(feature = "heapless")]
+#[derive(Clone, Debug)]
+pub struct Flush<const N: usize> {
+    cmds: heapless::Vec<SettingsWriteCmd, N>,
+    next: usize,
+    writes_left: usize,
+    persist: &'static [SettingsWriteCmd],
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> CommandQueue<N> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a command.
+    ///
+    /// The command is handed back if the queue is full, or if it's a flash
+    /// command: those are emitted by [`CommandQueue::flush`].
+    pub fn push(
+        &mut self,
+        cmd: SettingsWriteCmd,
+    ) -> Result<(), SettingsWriteCmd> {
+        if cmd.category() == SettingsCategory::Flash {
+            return Err(cmd);
+        }
+
+        self.cmds.push(cmd)
+    }
+
+    /// Returns the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.cmds.len()
+    }
+
+    /// Returns `true` if no command is queued.
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+
+    /// Empty the queue, returning the frames to send in order.
+    pub fn flush(&mut self) -> Flush<N> {
+        let cmds = core::mem::take(&mut self.cmds);
+        let writes_left = cmds
+            .iter()
+            .filter(|cmd| cmd.category() == SettingsCategory::Write)
+            .count();
+
+        Flush {
+            cmds,
+            next: 0,
+            writes_left,
+            persist: &[],
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Iterator for Flush<N> {
+    type Item = Result<[u8; 11], CmdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((cmd, rest)) = self.persist.split_first() {
+            self.persist = rest;
+            return Some((*cmd).try_into());
+        }
+
+        let cmd = *self.cmds.get(self.next)?;
+        self.next += 1;
+        if cmd.category() == SettingsCategory::Write {
+            self.writes_left -= 1;
+            if self.writes_left == 0 {
+                self.persist = &PERSIST;
+            }
+        }
+
+        Some(cmd.try_into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "heapless")]
+    use crate::profile::Profile;
+    #[cfg(feature = "heapless")]
+    use crate::types::FieldWeakening;
+    #[cfg(feature = "heapless")]
+    use crate::types::TorqueGain;
+
+    fn frame(cmd: SettingsWriteCmd) -> [u8; 11] {
+        cmd.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_transaction_persists_after_writes() {
+        use SettingsWriteCmd::*;
+
+        let mut frames = [[0x0; 11]; 4];
+        let mut len = 0;
+        let result = FlashTransaction::<CmdError>::run(
+            |f| {
+                frames[len] = f;
+                len += 1;
+                Ok(())
+            },
+            |tx| {
+                tx.write(SetAutoLock(true))?;
+                tx.write(SetLight(false))
+            },
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            frames,
+            [
+                frame(SetAutoLock(true)),
+                frame(SetLight(false)),
+                frame(WriteFlash),
+                frame(CloseFlash)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_skips_flash_on_failed_writes() {
+        let mut len = 0;
+        let result = FlashTransaction::run(
+            |_| {
+                len += 1;
+                Ok(())
+            },
+            |_| Err(CmdError::StrictlyRead),
+        );
+
+        assert_eq!(result, Err(CmdError::StrictlyRead));
+        assert_eq!(len, 0);
+
+        // Writes already emitted aren't persisted, and the flash isn't closed.
+        let mut frames = [[0x0; 11]; 1];
+        let mut len = 0;
+        let result = FlashTransaction::run(
+            |f| {
+                frames[len] = f;
+                len += 1;
+                Ok(())
+            },
+            |tx| {
+                tx.write(SettingsWriteCmd::SetLight(true))?;
+                Err(CmdError::StrictlyRead)
+            },
+        );
+
+        assert_eq!(result, Err(CmdError::StrictlyRead));
+        assert_eq!(frames, [frame(SettingsWriteCmd::SetLight(true))]);
+    }
+
+    #[test]
+    fn test_transaction_rejects_flash() {
+        for cmd in PERSIST {
+            let mut len = 0;
+            let result = FlashTransaction::run(
+                |_| {
+                    len += 1;
+                    Ok(())
+                },
+                |tx| tx.write(cmd),
+            );
+
+            assert_eq!(result, Err(CmdError::FlashManaged), "{cmd:?}");
+            assert_eq!(len, 0, "{cmd:?}");
+        }
+    }
+
+    #[test]
+    fn test_transaction_closes_on_error() {
+        use SettingsWriteCmd::*;
+
+        let mut frames = [[0x0; 11]; 3];
+        let mut len = 0;
+        let result = FlashTransaction::run(
+            |f| {
+                frames[len] = f;
+                len += 1;
+                match f == frame(WriteFlash) {
+                    true => Err(CmdError::BadChecksum),
+                    false => Ok(()),
+                }
+            },
+            |tx| tx.write(SetLight(true)),
+        );
+
+        assert_eq!(result, Err(CmdError::BadChecksum));
+        assert_eq!(
+            frames,
+            [frame(SetLight(true)), frame(WriteFlash), frame(CloseFlash)]
+        );
+    }
+
+    #[cfg(feature = "heapless")]
+    fn flush<const N: usize>(queue: &mut CommandQueue<N>) -> heapless::Vec<[u8; 11], 16> {
+        queue.flush().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_queue_brackets_writes() {
+        use SettingsWriteCmd::*;
+
+        let mut queue = CommandQueue::<8>::new();
+        queue.push(ReadAutoLock).unwrap();
+        queue.push(SetAutoLock(true)).unwrap();
+        queue.push(ReadTorqueGain).unwrap();
+        queue.push(SetLight(false)).unwrap();
+        queue.push(ReadMaxAssistedSpeed).unwrap();
+
+        assert_eq!(
+            flush(&mut queue),
+            [
+                frame(ReadAutoLock),
+                frame(SetAutoLock(true)),
+                frame(ReadTorqueGain),
+                frame(SetLight(false)),
+                frame(WriteFlash),
+                frame(CloseFlash),
+                frame(ReadMaxAssistedSpeed),
+            ]
+        );
+        assert!(queue.is_empty());
+        assert_eq!(queue.flush().count(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_queue_without_writes_skips_flash() {
+        use SettingsWriteCmd::*;
+
+        let mut queue = CommandQueue::<2>::new();
+        queue.push(ReadAutoLock).unwrap();
+        queue.push(ReadTorqueGain).unwrap();
+
+        assert_eq!(
+            flush(&mut queue),
+            [frame(ReadAutoLock), frame(ReadTorqueGain)]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_queue_rejects() {
+        use SettingsWriteCmd::*;
+
+        let mut queue = CommandQueue::<1>::new();
+        assert_eq!(queue.push(WriteFlash), Err(WriteFlash));
+        assert_eq!(queue.push(CloseFlash), Err(CloseFlash));
+        assert_eq!(queue.push(SetLight(true)), Ok(()));
+        assert_eq!(queue.push(SetLight(false)), Err(SetLight(false)));
+        assert_eq!(queue.len(), 1);
+
+        assert_eq!(
+            flush(&mut queue),
+            [frame(SetLight(true)), frame(WriteFlash), frame(CloseFlash)]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_persists_like_profile() {
+        let profile = Profile {
+            field_weakening: Some(FieldWeakening::new(20).unwrap()),
+            torque_gain: Some(TorqueGain::new(5).unwrap()),
+            ..Default::default()
+        };
+        let writes = [
+            SettingsWriteCmd::SetFieldWeakening(profile.field_weakening.unwrap()),
+            SettingsWriteCmd::SetTorqueGain(profile.torque_gain.unwrap()),
+        ];
+
+        let mut transaction = heapless::Vec::<[u8; 11], 16>::new();
+        FlashTransaction::<CmdError>::run(
+            |f| {
+                transaction.push(f).unwrap();
+                Ok(())
+            },
+            |tx| writes.into_iter().try_for_each(|cmd| tx.write(cmd)),
+        )
+        .unwrap();
+
+        let mut queue = CommandQueue::<2>::new();
+        for cmd in writes {
+            queue.push(cmd).unwrap();
+        }
+
+        let expected: heapless::Vec<[u8; 11], 16> = profile.frames().map(Result::unwrap).collect();
+        for (name, frames) in [("transaction", transaction), ("queue", flush(&mut queue))] {
+            assert_eq!(frames, expected, "{name}");
+        }
+    }
+}
@@ -0,0 +1,646 @@
+use core::fmt;
+
+use crate::error::CmdError;
+use crate::mode::CmdMode;
+use crate::transport::Command;
+use crate::utils::crc16;
+use crate::utils::packetize;
+use crate::utils::packetize_with;
+use crate::utils::verify_checksum;
+use crate::utils::verify_checksum_with;
+use crate::utils::CHECKSUM_LEN;
+use crate::utils::FRAME_LEN;
+
+/// A frame ready to be sent to, or received from the bike.
+///
+/// The frame carries the write mode it must be sent with, so it can't be
+/// sent expecting a response the bike will never give.
+///
+/// Both `Debug` and `Display` render the bytes as hex followed by whether
+/// the checksum is valid, e.g. `0A 10 00 04 00 01 02 00 19 15 2E crc ok`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    bytes: [u8; 11],
+    mode: CmdMode,
+}
+
+impl Frame {
+    /// Creates a frame to be sent with the given write mode.
+    pub fn new(
+        bytes: [u8; 11],
+        mode: CmdMode,
+    ) -> Self {
+        Self { bytes, mode }
+    }
+
+    /// Creates a frame from a body, appending its checksum.
+    ///
+    /// Unlike [`Frame::parse`], there is no checksum to check: it is
+    /// computed from the body. The frame doesn't expect a response.
+    pub fn from_body(body: [u8; 9]) -> Self {
+        Self::new(packetize(&body), CmdMode::WriteOnly)
+    }
+
+    /// Creates a frame from a body, appending its checksum computed with
+    /// `algorithm`.
+    ///
+    /// The frame doesn't expect a response. Its `Display` still checks the
+    /// checksum with the default algorithm.
+    pub fn from_body_with(
+        body: [u8; 9],
+        algorithm: ChecksumAlgorithm,
+    ) -> Self {
+        Self::new(packetize_with(&body, algorithm), CmdMode::WriteOnly)
+    }
+
+    /// Creates a frame from received bytes, checking their checksum.
+    ///
+    /// The frame doesn't expect a response.
+    pub fn parse(bytes: [u8; 11]) -> Result<Self, CmdError> {
+        Self::parse_with(bytes, ChecksumAlgorithm::default())
+    }
+
+    /// Creates a frame from received bytes, checking their checksum was
+    /// computed with `algorithm`.
+    ///
+    /// The frame doesn't expect a response.
+    pub fn parse_with(
+        bytes: [u8; 11],
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Self, CmdError> {
+        match verify_checksum_with(&bytes, algorithm) {
+            true => Ok(Self::new(bytes, CmdMode::WriteOnly)),
+            false => Err(CmdError::BadChecksum),
+        }
+    }
+
+    /// Encode a command into a frame, along with its write mode.
+    pub fn from_command(cmd: &(impl Command + ?Sized)) -> Result<Self, CmdError> {
+        Ok(Self::new(cmd.to_frame()?, cmd.mode()))
+    }
+
+    /// Returns the bytes of the frame.
+    pub fn as_bytes(&self) -> &[u8; 11] {
+        &self.bytes
+    }
+
+    /// Returns the bytes of the frame, for transports taking owned buffers.
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> alloc::vec::Vec<u8> {
+        self.bytes.to_vec()
+    }
+
+    /// Returns the write mode the frame must be sent with.
+    pub fn mode(&self) -> CmdMode {
+        self.mode
+    }
+
+    /// Returns whether the bike responds to the frame.
+    pub fn expects_response(&self) -> bool {
+        self.mode == CmdMode::WriteWithResponse
+    }
+}
+
+impl From<[u8; 9]> for Frame {
+    fn from(body: [u8; 9]) -> Self {
+        Self::from_body(body)
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        for byte in self.bytes {
+            write!(f, "{byte:02X} ")?;
+        }
+
+        match verify_checksum(&self.bytes) {
+            true => write!(f, "crc ok"),
+            false => write!(f, "crc bad"),
+        }
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "Frame({self})")
+    }
+}
+
+/// A frame rendered in binary with `{:b}`, one group of 8 bits per byte, to
+/// spot individual flags.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameBits<'a>(pub &'a [u8; 11]);
+
+impl fmt::Binary for FrameBits<'_> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{byte:08b}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Algorithm used to checksum frame bodies.
+///
+/// Only CRC-16/MODBUS is known so far, but firmware revisions may frame
+/// commands differently, starting with the byte order of the checksum.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// CRC-16/MODBUS, low byte first, as used by the Cowboy app.
+    #[default]
+    Crc16Modbus,
+
+    /// CRC-16/MODBUS, high byte first, for firmware expecting the checksum
+    /// the other way around.
+    ///
+    /// Select it with [`Frame::from_body_with`] and [`Frame::parse_with`].
+    Crc16ModbusBigEndian,
+}
+
+impl ChecksumAlgorithm {
+    /// Compute the checksum of a body.
+    pub fn checksum(
+        &self,
+        body: &[u8],
+    ) -> Checksum {
+        match self {
+            Self::Crc16Modbus => Checksum(crc16(body).to_le_bytes()),
+            Self::Crc16ModbusBigEndian => Checksum(crc16(body).to_be_bytes()),
+        }
+    }
+}
+
+/// The checksum of a frame body, in the byte order it is sent to the bike.
+///
+/// Comparing two checksums takes the same time whichever bytes differ.
+#[derive(Clone, Copy, Debug, Eq)]
+pub struct Checksum(pub [u8; 2]);
+
+impl Checksum {
+    /// Compute the checksum of a body with the default algorithm.
+    pub fn of(body: &[u8]) -> Self {
+        ChecksumAlgorithm::default().checksum(body)
+    }
+
+    /// Check that this is the checksum of `body`.
+    pub fn verify(
+        &self,
+        body: &[u8],
+    ) -> bool {
+        Self::of(body) == *self
+    }
+}
+
+impl PartialEq for Checksum {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        ((self.0[0] ^ other.0[0]) | (self.0[1] ^ other.0[1])) == 0
+    }
+}
+
+/// Builds a frame from a body and the given checksum, without checking it.
+///
+/// Meant for feeding corrupt frames to decoders in tests.
+#[doc(hidden)]
+pub fn frame_with_checksum(
+    body: [u8; 9],
+    checksum: [u8; 2],
+) -> [u8; 11] {
+    let mut frame = [0x0; 11];
+    frame[..9].copy_from_slice(&body);
+    frame[9..].copy_from_slice(&checksum);
+    frame
+}
+
+/// Reassembles frames notified in fragments.
+///
+/// A notification can carry less than a frame when the link has a small
+/// MTU, in which case the frame is split across consecutive notifications.
+/// A notification can also end a frame and start the next one: the bytes
+/// past the end of a frame are kept as the start of the next.
+///
+/// Every frame is `N` bytes long, its checksum included: 11 for the frames
+/// of this crate, more for longer responses.
+#[derive(Clone, Copy, Debug)]
+pub struct Reassembler<const N: usize = FRAME_LEN> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Reassembler<N> {
+    /// Creates a reassembler waiting for the start of a frame.
+    pub fn new() -> Self {
+        const { assert!(N > CHECKSUM_LEN, "a frame must be longer than its checksum") };
+        Self {
+            buf: [0x0; N],
+            len: 0,
+        }
+    }
+
+    /// Feed the next fragment.
+    ///
+    /// Returns the frames the fragment completes, each checked against its
+    /// checksum. The fragment is consumed as the frames are iterated over:
+    /// dropping the iterator early discards the frames not yet returned,
+    /// but still keeps the start of the next one.
+    pub fn push<'a>(
+        &'a mut self,
+        fragment: &'a [u8],
+    ) -> Frames<'a, N> {
+        Frames {
+            reassembler: self,
+            fragment,
+        }
+    }
+
+    /// Discard the fragments received so far.
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for Reassembler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frames completed by a fragment, see [`Reassembler::push`].
+#[derive(Debug)]
+pub struct Frames<'a, const N: usize> {
+    reassembler: &'a mut Reassembler<N>,
+    fragment: &'a [u8],
+}
+
+impl<const N: usize> Iterator for Frames<'_, N> {
+    type Item = Result<[u8; N], CmdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reassembler { buf, len } = &mut *self.reassembler;
+        let (head, tail) = self.fragment.split_at(self.fragment.len().min(N - *len));
+        buf[*len..*len + head.len()].copy_from_slice(head);
+        *len += head.len();
+        self.fragment = tail;
+        if *len < N {
+            return None;
+        }
+
+        *len = 0;
+        let (body, checksum) = buf.split_at(N - CHECKSUM_LEN);
+        match ChecksumAlgorithm::default().checksum(body).0 == checksum {
+            true => Some(Ok(*buf)),
+            false => Some(Err(CmdError::BadChecksum)),
+        }
+    }
+}
+
+impl<const N: usize> Drop for Frames<'_, N> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+/// Compare the bodies of two frames, ignoring their checksums.
+pub fn bodies_equal(
+    a: &[u8; 11],
+    b: &[u8; 11],
+) -> bool {
+    a[..9] == b[..9]
+}
+
+/// Whether a frame is of the same command as a template, whatever its value.
+///
+/// Only the header (bytes 0-6) is compared, so a `SetLight(true)` frame
+/// matches a `SetLight(false)` template.
+pub fn matches_template(
+    frame: &[u8; 11],
+    template: &[u8; 11],
+) -> bool {
+    frame[..7] == template[..7]
+}
+
+/// Render a frame as annotated bytes, for debugging and support dumps.
+///
+/// The output looks like
+/// `addr=0x0A func=0x10 reg=0x0004 count=1 bytes=2 val=0x0019 crc=0x2E15`.
+#[cfg(feature = "heapless")]
+pub fn annotate(frame: &[u8; 11]) -> heapless::String<128> {
+    let mut annotation = heapless::String::new();
+
+    // The longest annotation is well under 128 bytes, so this can't fail.
+    let _ = annotate_into(frame, &mut annotation);
+
+    annotation
+}
+
+/// Write the annotation of a frame, as rendered by [`annotate`], to `w`.
+///
+/// This writes straight to the caller's buffer or UART, without the
+/// `heapless` feature.
+pub fn annotate_into(
+    frame: &[u8; 11],
+    w: &mut impl fmt::Write,
+) -> fmt::Result {
+    write!(
+        w,
+        "addr=0x{:02X} func=0x{:02X} reg=0x{:04X} count={} bytes={} val=0x{:04X} crc=0x{:04X}",
+        frame[0],
+        frame[1],
+        u16::from_be_bytes([frame[2], frame[3]]),
+        u16::from_be_bytes([frame[4], frame[5]]),
+        frame[6],
+        u16::from_be_bytes([frame[7], frame[8]]),
+        u16::from_le_bytes([frame[9], frame[10]]),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "heapless")]
+    use core::fmt::Write;
+
+    use super::*;
+    use crate::SettingsWriteCmd;
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_annotate() {
+        let frame = SettingsWriteCmd::SetMaxAssistedSpeed(Default::default())
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            annotate(&frame),
+            "addr=0x0A func=0x10 reg=0x0004 count=1 bytes=2 val=0x0019 crc=0x2E15"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_annotate_into() {
+        let frame = SettingsWriteCmd::ReadFieldWeakening.try_into().unwrap();
+
+        let mut out = heapless::String::<128>::new();
+        annotate_into(&frame, &mut out).unwrap();
+        assert_eq!(out, annotate(&frame));
+        assert_eq!(
+            out,
+            "addr=0x01 func=0x03 reg=0x0081 count=1 bytes=0 val=0x0000 crc=0x9858"
+        );
+
+        // Too small a buffer surfaces as an error, instead of a cut annotation.
+        let mut short = heapless::String::<16>::new();
+        assert_eq!(annotate_into(&frame, &mut short), Err(fmt::Error));
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_frame_format() {
+        let mut bytes: [u8; 11] = SettingsWriteCmd::SetMaxAssistedSpeed(Default::default())
+            .try_into()
+            .unwrap();
+
+        let mut out = heapless::String::<64>::new();
+        write!(out, "{:?}", Frame::new(bytes, CmdMode::WriteOnly)).unwrap();
+        assert_eq!(out, "Frame(0A 10 00 04 00 01 02 00 19 15 2E crc ok)");
+
+        bytes[10] = 0x0;
+        out.clear();
+        write!(out, "{}", Frame::new(bytes, CmdMode::WriteOnly)).unwrap();
+        assert_eq!(out, "0A 10 00 04 00 01 02 00 19 15 00 crc bad");
+    }
+
+    #[test]
+    fn test_bodies_equal() {
+        let a: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+        let b: [u8; 11] = SettingsWriteCmd::SetLight(false).try_into().unwrap();
+
+        let mut corrupted = a;
+        corrupted[9] ^= 0xFF;
+        corrupted[10] ^= 0xFF;
+
+        assert!(bodies_equal(&a, &a));
+        assert!(bodies_equal(&a, &corrupted));
+        assert!(!bodies_equal(&a, &b));
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_frame_bits() {
+        let frame = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+
+        let mut out = heapless::String::<128>::new();
+        write!(out, "{:b}", FrameBits(&frame)).unwrap();
+        assert_eq!(
+            out,
+            "00001010 00010000 00000000 00000001 00000000 00000001 \
+             00000010 00000000 00000001 00010101 01110001"
+        );
+    }
+
+    #[test]
+    fn test_frame_bytes() {
+        let bytes = SettingsWriteCmd::SetAutoLock(true).try_into().unwrap();
+        let frame = Frame::new(bytes, CmdMode::WriteWithResponse);
+
+        assert_eq!(frame.as_bytes(), &bytes);
+        assert_eq!(Frame::from_body([0x0; 9]).as_bytes()[..9], [0x0; 9]);
+        #[cfg(feature = "alloc")]
+        assert_eq!(frame.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_matches_template() {
+        let frame = |cmd: SettingsWriteCmd| <[u8; 11]>::try_from(cmd).unwrap();
+        let template = frame(SettingsWriteCmd::SetLight(false));
+
+        assert!(matches_template(
+            &frame(SettingsWriteCmd::SetLight(true)),
+            &template
+        ));
+        assert!(matches_template(&template, &template));
+        assert!(!matches_template(
+            &frame(SettingsWriteCmd::SetAutoLock(false)),
+            &template
+        ));
+        assert!(!matches_template(
+            &frame(SettingsWriteCmd::ReadAutoLock),
+            &frame(SettingsWriteCmd::SetAutoLock(false))
+        ));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let frame: [u8; 11] = SettingsWriteCmd::SetMaxAssistedSpeed(Default::default())
+            .try_into()
+            .unwrap();
+        let checksum = Checksum::of(&frame[..9]);
+
+        assert_eq!(checksum, Checksum([0x15, 0x2E]));
+        assert_ne!(checksum, Checksum([0x15, 0x2F]));
+        assert_ne!(checksum, Checksum([0x16, 0x2E]));
+        assert!(checksum.verify(&frame[..9]));
+        assert!(!checksum.verify(&frame[1..9]));
+    }
+
+    #[test]
+    fn test_frame_with_checksum() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+
+        let frame = frame_with_checksum(body, [0x15, 0x2E]);
+        assert_eq!(frame, *Frame::from_body(body).as_bytes());
+        assert!(SettingsWriteCmd::try_from(frame).is_ok());
+
+        let frame = frame_with_checksum(body, [0x2E, 0x15]);
+        assert!(!verify_checksum(&frame));
+        assert_eq!(
+            SettingsWriteCmd::try_from(frame),
+            Err(CmdError::BadChecksum)
+        );
+        assert_eq!(Frame::parse(frame), Err(CmdError::BadChecksum));
+    }
+
+    #[test]
+    fn test_frame_expects_response() {
+        let light = SettingsWriteCmd::SetLight(true);
+        let frame = Frame::from_command(&light).unwrap();
+        assert_eq!(frame.mode(), CmdMode::WriteOnly);
+        assert!(!frame.expects_response());
+        assert_eq!(*frame.as_bytes(), light.to_frame().unwrap());
+
+        let auto_lock = SettingsWriteCmd::SetAutoLock(true);
+        let frame = Frame::from_command(&auto_lock).unwrap();
+        assert_eq!(frame.mode(), CmdMode::WriteWithResponse);
+        assert!(frame.expects_response());
+
+        assert!(!Frame::parse(*frame.as_bytes()).unwrap().expects_response());
+        assert!(Frame::new(*frame.as_bytes(), CmdMode::WriteWithResponse).expects_response());
+    }
+
+    #[test]
+    fn test_reassembler() {
+        let frame: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+        let mut reassembler = Reassembler::new();
+
+        assert_eq!(reassembler.push(&frame[..4]).next(), None);
+        assert_eq!(reassembler.push(&[]).next(), None);
+        assert_eq!(reassembler.push(&frame[4..10]).next(), None);
+        assert_eq!(reassembler.push(&frame[10..]).next(), Some(Ok(frame)));
+
+        assert_eq!(reassembler.push(&frame).next(), Some(Ok(frame)));
+
+        let mut corrupted = frame;
+        corrupted[10] ^= 0xFF;
+        assert_eq!(reassembler.push(&corrupted[..5]).next(), None);
+        assert_eq!(
+            reassembler.push(&corrupted[5..]).next(),
+            Some(Err(CmdError::BadChecksum))
+        );
+
+        assert_eq!(reassembler.push(&frame[..3]).next(), None);
+        reassembler.reset();
+        assert_eq!(reassembler.push(&frame).next(), Some(Ok(frame)));
+    }
+
+    #[test]
+    fn test_reassembler_carries_over() {
+        let frame: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+        let mut reassembler = Reassembler::new();
+
+        let mut fragment = [0x0; 26];
+        fragment[..11].copy_from_slice(&frame);
+        fragment[11..22].copy_from_slice(&frame);
+        fragment[22..].copy_from_slice(&frame[..4]);
+
+        assert_eq!(reassembler.push(&fragment[..3]).next(), None);
+        let mut frames = reassembler.push(&fragment[3..]);
+        assert_eq!(frames.next(), Some(Ok(frame)));
+        assert_eq!(frames.next(), Some(Ok(frame)));
+        assert_eq!(frames.next(), None);
+        drop(frames);
+        assert_eq!(reassembler.push(&frame[4..]).next(), Some(Ok(frame)));
+
+        // Frames not iterated over are dropped, but not the next one.
+        drop(reassembler.push(&fragment));
+        assert_eq!(reassembler.push(&frame[4..]).next(), Some(Ok(frame)));
+    }
+
+    #[test]
+    fn test_reassembler_longer_frames() {
+        let mut frame = [0x0; 16];
+        frame[..14].copy_from_slice(b"longer frames!");
+        let checksum = ChecksumAlgorithm::default().checksum(&frame[..14]);
+        frame[14..].copy_from_slice(&checksum.0);
+
+        let mut reassembler = Reassembler::<16>::new();
+        assert_eq!(reassembler.push(&frame[..11]).next(), None);
+        assert_eq!(reassembler.push(&frame[11..]).next(), Some(Ok(frame)));
+    }
+
+    #[test]
+    fn test_frame_from_body() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+        let frame = Frame::from_body(body);
+
+        assert_eq!(frame.as_bytes()[..9], body);
+        assert_eq!(frame.as_bytes()[9..], [0x15, 0x2E]);
+        assert_eq!(frame.mode(), CmdMode::WriteOnly);
+        assert_eq!(Frame::from(body), frame);
+        assert_eq!(Frame::parse(*frame.as_bytes()), Ok(frame));
+
+        let mut corrupted = *frame.as_bytes();
+        corrupted[9] ^= 0xFF;
+        assert_eq!(Frame::parse(corrupted), Err(CmdError::BadChecksum));
+    }
+
+    #[test]
+    fn test_frame_with_algorithm() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+        let algorithm = ChecksumAlgorithm::default();
+
+        let frame = Frame::from_body_with(body, algorithm);
+        assert_eq!(frame, Frame::from_body(body));
+        assert_eq!(Frame::parse_with(*frame.as_bytes(), algorithm), Ok(frame));
+
+        let mut corrupted = *frame.as_bytes();
+        corrupted[9] ^= 0xFF;
+        assert_eq!(
+            Frame::parse_with(corrupted, algorithm),
+            Err(CmdError::BadChecksum)
+        );
+    }
+
+    #[test]
+    fn test_frame_big_endian_checksum() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+        let algorithm = ChecksumAlgorithm::Crc16ModbusBigEndian;
+
+        let frame = Frame::from_body_with(body, algorithm);
+        assert_eq!(frame.as_bytes()[..9], body);
+        assert_eq!(frame.as_bytes()[9..], [0x2E, 0x15]);
+        assert_eq!(Frame::parse_with(*frame.as_bytes(), algorithm), Ok(frame));
+        assert_eq!(Frame::parse(*frame.as_bytes()), Err(CmdError::BadChecksum));
+
+        let little_endian = Frame::from_body(body);
+        assert_eq!(
+            Frame::parse_with(*little_endian.as_bytes(), algorithm),
+            Err(CmdError::BadChecksum)
+        );
+    }
+}
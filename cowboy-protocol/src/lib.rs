@@ -1,10 +1,27 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub use cmd::*;
 pub use mode::*;
+pub use utils::checked_write_value;
+pub use utils::FrameBuilder;
 
 pub mod cmd;
+pub mod confirm;
 pub mod error;
+pub mod flash;
+pub mod frame;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod mode;
+pub mod poll;
+pub mod prelude;
+pub mod profile;
+pub mod registers;
+pub mod retry;
+pub mod transport;
 pub mod types;
+pub mod units;
 mod utils;
@@ -0,0 +1,173 @@
+use crate::cmd::CowboyLockCmd;
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+use crate::types::FieldWeakening;
+use crate::types::HallInterpolation;
+use crate::types::LockState;
+use crate::types::Speed;
+use crate::types::TorqueGain;
+use crate::types::TorqueMode;
+use crate::utils::packetize;
+use crate::utils::BODY_LEN;
+
+/// A fake bike, to test a client end to end without hardware.
+///
+/// Frames written to the bike are decoded and applied to its state, and
+/// reads are answered with a response frame. Responses echo the read header
+/// and carry the value in bytes 7-8, as it was last written.
+#[derive(Clone, Copy, Debug)]
+pub struct MockCowboy {
+    lock: LockState,
+    light: bool,
+    auto_lock: bool,
+    max_assisted_speed: Speed,
+    field_weakening: FieldWeakening,
+    hall_interpolation: HallInterpolation,
+    torque_gain: TorqueGain,
+    torque_mode: TorqueMode,
+}
+
+impl Default for MockCowboy {
+    fn default() -> Self {
+        Self {
+            lock: LockState::Locked,
+            light: false,
+            auto_lock: false,
+            max_assisted_speed: Default::default(),
+            field_weakening: Default::default(),
+            hall_interpolation: Default::default(),
+            // TorqueGain has no default, and 0 Nm is always in range.
+            torque_gain: TorqueGain::new(0).unwrap(),
+            torque_mode: Default::default(),
+        }
+    }
+}
+
+impl MockCowboy {
+    /// Handle a frame written to the settings characteristic.
+    ///
+    /// Returns the response frame for reads, and `None` otherwise. Raw
+    /// register reads aren't answered, since the mock has no register map.
+    pub fn write_settings(
+        &mut self,
+        frame: [u8; 11],
+    ) -> Result<Option<[u8; 11]>, CmdError> {
+        use SettingsWriteCmd::*;
+
+        let current = match SettingsWriteCmd::try_from(frame)? {
+            SetLight(v) => return self.set(|bike| bike.light = v),
+            SetAutoLock(v) => return self.set(|bike| bike.auto_lock = v),
+            SetMaxAssistedSpeed(v) => return self.set(|bike| bike.max_assisted_speed = v),
+            SetFieldWeakening(v) => return self.set(|bike| bike.field_weakening = v),
+            SetHallInterpolation(v) => return self.set(|bike| bike.hall_interpolation = v),
+            SetTorqueGain(v) => return self.set(|bike| bike.torque_gain = v),
+            SetMotorTorqueMode(v) => return self.set(|bike| bike.torque_mode = v),
+            WriteFlash | CloseFlash | ReadRegister(_) | RawWrite { .. } | RawRead { .. } => {
+                return Ok(None)
+            }
+            ReadAutoLock => SetAutoLock(self.auto_lock),
+            ReadMaxAssistedSpeed => SetMaxAssistedSpeed(self.max_assisted_speed),
+            ReadFieldWeakening => SetFieldWeakening(self.field_weakening),
+            ReadHallInterpolation => SetHallInterpolation(self.hall_interpolation),
+            ReadTorqueGain => SetTorqueGain(self.torque_gain),
+            ReadMotorTorqueMode => SetMotorTorqueMode(self.torque_mode),
+        };
+
+        // The response is the write of the current value, with the read
+        // function code.
+        let write: [u8; 11] = current.try_into()?;
+        let mut body = [0x0; BODY_LEN];
+        body.copy_from_slice(&write[..BODY_LEN]);
+        body[1] = 0x3;
+
+        Ok(Some(packetize(&body)))
+    }
+
+    /// Handle a frame written to the lock characteristic.
+    pub fn write_lock(
+        &mut self,
+        frame: [u8; 11],
+    ) -> Result<(), CmdError> {
+        match CowboyLockCmd::try_from(frame)? {
+            CowboyLockCmd::SetLock(true) => self.lock = LockState::Locked,
+            CowboyLockCmd::SetLock(false) => self.lock = LockState::Unlocked,
+            CowboyLockCmd::ReadLock => return Err(CmdError::StrictlyRead),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the lock state of the bike.
+    pub fn lock(&self) -> LockState {
+        self.lock
+    }
+
+    /// Returns whether the light is on.
+    pub fn light(&self) -> bool {
+        self.light
+    }
+
+    fn set(
+        &mut self,
+        update: impl FnOnce(&mut Self),
+    ) -> Result<Option<[u8; 11]>, CmdError> {
+        update(self);
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::confirm::ConfirmedWrite;
+    use crate::utils::verify_checksum;
+
+    fn frame(cmd: SettingsWriteCmd) -> [u8; 11] {
+        cmd.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        let mut bike = MockCowboy::default();
+        let weakening = FieldWeakening::new(20).unwrap();
+
+        let write = frame(SettingsWriteCmd::SetFieldWeakening(weakening));
+        assert_eq!(bike.write_settings(write), Ok(None));
+
+        let response = bike
+            .write_settings(frame(SettingsWriteCmd::ReadFieldWeakening))
+            .unwrap()
+            .unwrap();
+        assert!(verify_checksum(&response));
+        assert_eq!(response[..4], [0x1, 0x3, 0x0, 0x81]);
+        assert_eq!(response[7..9], write[7..9]);
+        assert!(ConfirmedWrite::new(weakening).verify_response(&response));
+
+        bike.write_settings(frame(SettingsWriteCmd::SetAutoLock(true)))
+            .unwrap();
+        let response = bike
+            .write_settings(frame(SettingsWriteCmd::ReadAutoLock))
+            .unwrap()
+            .unwrap();
+        assert_eq!(response[..4], [0xA, 0x3, 0x0, 0x0]);
+        assert_eq!(response[7..9], [0x0, 0x1]);
+    }
+
+    #[test]
+    fn test_write_lock_and_light() {
+        let mut bike = MockCowboy::default();
+        assert_eq!(bike.lock(), LockState::Locked);
+
+        let unlock: [u8; 11] = CowboyLockCmd::SetLock(false).try_into().unwrap();
+        bike.write_lock(unlock).unwrap();
+        assert_eq!(bike.lock(), LockState::Unlocked);
+
+        bike.write_settings(frame(SettingsWriteCmd::SetLight(true)))
+            .unwrap();
+        assert!(bike.light());
+
+        let mut corrupted = unlock;
+        corrupted[10] ^= 0xFF;
+        assert_eq!(bike.write_lock(corrupted), Err(CmdError::BadChecksum));
+    }
+}
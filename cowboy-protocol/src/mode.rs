@@ -1,7 +1,8 @@
+use core::fmt;
 use core::str::FromStr;
 
 /// The mode for a given command.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CmdMode {
     /// Write without expecting a response.
     WriteOnly,
@@ -10,6 +11,25 @@ pub enum CmdMode {
     WriteWithResponse,
 }
 
+impl CmdMode {
+    /// Returns the name of the mode, as accepted by [`CmdMode::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WriteOnly => "write",
+            Self::WriteWithResponse => "write_with_response",
+        }
+    }
+}
+
+impl fmt::Display for CmdMode {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl FromStr for CmdMode {
     type Err = &'static str;
 
@@ -21,3 +41,18 @@ impl FromStr for CmdMode {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mode_str_round_trip() {
+        for mode in [CmdMode::WriteOnly, CmdMode::WriteWithResponse] {
+            assert_eq!(CmdMode::from_str(mode.as_str()), Ok(mode));
+        }
+
+        assert_eq!(CmdMode::WriteOnly.as_str(), "write");
+        assert_eq!(CmdMode::WriteWithResponse.as_str(), "write_with_response");
+    }
+}
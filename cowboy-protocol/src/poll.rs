@@ -0,0 +1,135 @@
+use core::time::Duration;
+
+use crate::cmd::CowboyService;
+
+/// Round-robin schedule of read commands to poll the bike with.
+///
+/// The plan doesn't keep time itself: the client loop passes the current
+/// time, from any monotonic clock, to [`PollPlan::poll`] and sends whatever
+/// command it returns. At most one command is returned per interval, so each
+/// command is sent once every `interval * commands.len()`.
+#[derive(Debug, Clone)]
+pub struct PollPlan<'a> {
+    cmds: &'a [CowboyService],
+    interval: Duration,
+    next: usize,
+    last_sent: Option<Duration>,
+}
+
+impl<'a> PollPlan<'a> {
+    /// Creates a plan polling the given commands, one every `interval`.
+    pub fn new(
+        cmds: &'a [CowboyService],
+        interval: Duration,
+    ) -> Self {
+        Self {
+            cmds,
+            interval,
+            next: 0,
+            last_sent: None,
+        }
+    }
+
+    /// Returns the command to send at `now`, if one is due.
+    pub fn poll(
+        &mut self,
+        now: Duration,
+    ) -> Option<CowboyService> {
+        if now < self.next_due()? {
+            return None;
+        }
+
+        let cmd = self.cmds[self.next];
+        self.next = (self.next + 1) % self.cmds.len();
+        self.last_sent = Some(now);
+        Some(cmd)
+    }
+
+    /// Returns the time at which the next command is due.
+    ///
+    /// `None` is returned if there is nothing to poll. A due time past
+    /// [`Duration::MAX`] saturates to it.
+    pub fn next_due(&self) -> Option<Duration> {
+        if self.cmds.is_empty() {
+            return None;
+        }
+
+        Some(match self.last_sent {
+            Some(last_sent) => last_sent.saturating_add(self.interval),
+            None => Duration::ZERO,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd::CowboyCharacteristic;
+    use crate::cmd::CowboyLockCmd;
+    use crate::cmd::SettingsCharacteristic;
+    use crate::cmd::SettingsWriteCmd;
+
+    fn is_read_lock(cmd: Option<CowboyService>) -> bool {
+        matches!(
+            cmd,
+            Some(CowboyService::Cowboy(CowboyCharacteristic::Lock(
+                CowboyLockCmd::ReadLock
+            )))
+        )
+    }
+
+    fn is_read_auto_lock(cmd: Option<CowboyService>) -> bool {
+        matches!(
+            cmd,
+            Some(CowboyService::Settings(SettingsCharacteristic::Write(
+                SettingsWriteCmd::ReadAutoLock
+            )))
+        )
+    }
+
+    #[test]
+    fn test_poll_round_robin() {
+        let cmds = [
+            CowboyService::Cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::ReadLock)),
+            CowboyService::from(SettingsWriteCmd::ReadAutoLock),
+        ];
+
+        let mut plan = PollPlan::new(&cmds, Duration::from_secs(1));
+        let at = Duration::from_secs;
+
+        assert!(is_read_lock(plan.poll(at(0))));
+        assert!(is_read_auto_lock(plan.poll(at(1))));
+        assert!(is_read_lock(plan.poll(at(2))));
+        assert!(is_read_auto_lock(plan.poll(at(3))));
+    }
+
+    #[test]
+    fn test_poll_interval_gating() {
+        let cmds = [CowboyService::from(SettingsWriteCmd::ReadAutoLock)];
+        let mut plan = PollPlan::new(&cmds, Duration::from_millis(500));
+        let at = Duration::from_millis;
+
+        assert_eq!(plan.next_due(), Some(at(0)));
+        assert!(is_read_auto_lock(plan.poll(at(100))));
+        assert_eq!(plan.next_due(), Some(at(600)));
+        assert!(plan.poll(at(599)).is_none());
+        assert!(is_read_auto_lock(plan.poll(at(600))));
+        assert!(plan.poll(at(700)).is_none());
+
+        let mut empty = PollPlan::new(&[], Duration::from_millis(500));
+        assert_eq!(empty.next_due(), None);
+        assert!(empty.poll(at(1_000)).is_none());
+    }
+
+    #[test]
+    fn test_poll_due_saturates() {
+        let cmds = [CowboyService::from(SettingsWriteCmd::ReadAutoLock)];
+        let mut plan = PollPlan::new(&cmds, Duration::MAX);
+
+        assert!(is_read_auto_lock(plan.poll(Duration::from_secs(1))));
+        assert_eq!(plan.next_due(), Some(Duration::MAX));
+        assert!(plan.poll(Duration::from_secs(2)).is_none());
+        assert!(is_read_auto_lock(plan.poll(Duration::MAX)));
+        assert_eq!(plan.next_due(), Some(Duration::MAX));
+    }
+}
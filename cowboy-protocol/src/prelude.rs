@@ -0,0 +1,63 @@
+//! Common imports for building and encoding commands.
+//!
+//! ```
+//! use cowboy_protocol::prelude::*;
+//!
+//! let frame: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into()?;
+//! assert_eq!(encode(SettingsWriteCmd::SetLight(true))?, frame);
+//! # Ok::<(), CmdError>(())
+//! ```
+
+pub use crate::cmd::CowboyService;
+pub use crate::cmd::SettingsWriteCmd;
+pub use crate::error::CmdError;
+pub use crate::error::FieldError;
+pub use crate::frame::Frame;
+pub use crate::mode::CmdMode;
+pub use crate::transport::Command;
+pub use crate::types::FieldWeakening;
+pub use crate::types::HallInterpolation;
+pub use crate::types::Speed;
+pub use crate::types::TorqueGain;
+pub use crate::types::TorqueMode;
+
+/// Encode anything convertible into a frame.
+///
+/// This lets code sending frames accept commands, characteristics and
+/// setting values alike.
+pub fn encode<C: TryInto<[u8; 11], Error = CmdError>>(cmd: C) -> Result<[u8; 11], CmdError> {
+    cmd.try_into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd::CowboyLockCmd;
+
+    fn frames() -> Result<[[u8; 11]; 3], CmdError> {
+        let light: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into()?;
+        let service: [u8; 11] = CowboyService::from(SettingsWriteCmd::WriteFlash).try_into()?;
+        let lock: [u8; 11] = CowboyLockCmd::SetLock(true).try_into()?;
+
+        Ok([light, service, lock])
+    }
+
+    #[test]
+    fn test_encode() {
+        let [light, service, lock] = frames().unwrap();
+
+        assert_eq!(encode(SettingsWriteCmd::SetLight(true)), Ok(light));
+        assert_eq!(
+            encode(CowboyService::from(SettingsWriteCmd::WriteFlash)),
+            Ok(service)
+        );
+        assert_eq!(encode(CowboyLockCmd::SetLock(true)), Ok(lock));
+        assert_eq!(
+            encode(FieldWeakening::new(20).unwrap()),
+            encode(SettingsWriteCmd::SetFieldWeakening(
+                FieldWeakening::new(20).unwrap()
+            ))
+        );
+        assert_eq!(encode(CowboyLockCmd::ReadLock), Err(CmdError::StrictlyRead));
+    }
+}
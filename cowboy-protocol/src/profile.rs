@@ -0,0 +1,279 @@
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+use crate::flash::PERSIST;
+use crate::types::FieldWeakening;
+use crate::types::HallInterpolation;
+use crate::types::Speed;
+use crate::types::TorqueGain;
+use crate::types::TorqueMode;
+
+/// Fastest max assisted speed, in km/h, reachable without field weakening.
+const MAX_SPEED_WITHOUT_FIELD_WEAKENING: u8 = 29;
+
+/// Legal max assisted speed of a pedelec in the EU, in km/h.
+#[cfg(feature = "heapless")]
+const LEGAL_MAX_ASSISTED_SPEED: u8 = 25;
+
+/// A set of motor settings to be applied to the bike in one go.
+///
+/// Settings left as `None` are not written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Profile {
+    pub field_weakening: Option<FieldWeakening>,
+    pub hall_interpolation: Option<HallInterpolation>,
+    pub torque_gain: Option<TorqueGain>,
+    pub max_assisted_speed: Option<Speed>,
+    pub torque_mode: Option<TorqueMode>,
+}
+
+/// A setting combination that is valid, but probably not what was intended.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// The max assisted speed can't be reached without field weakening.
+    SpeedRequiresFieldWeakening,
+
+    /// The torque mode is speed-limited, but the max assisted speed is above
+    /// the legal cap of 25 km/h.
+    SpeedLimitedAboveLegalCap,
+
+    /// The torque mode is unlimited, but the max assisted speed is capped at
+    /// or below the legal 25 km/h anyway.
+    UnlimitedBelowLegalCap,
+}
+
+/// Iterator over the frames required to apply a [`Profile`].
+///
+/// Frames are yielded in a fixed order: field weakening, hall interpolation,
+/// torque gain, max assisted speed, torque mode, and finally `WriteFlash`
+/// followed by `CloseFlash` to persist the settings.
+#[derive(Debug, Clone)]
+pub struct ProfileFrames<'a> {
+    profile: &'a Profile,
+    step: usize,
+}
+
+impl Profile {
+    /// Returns `true` if no setting is configured.
+    pub fn is_empty(&self) -> bool {
+        self.field_weakening.is_none()
+            && self.hall_interpolation.is_none()
+            && self.torque_gain.is_none()
+            && self.max_assisted_speed.is_none()
+            && self.torque_mode.is_none()
+    }
+
+    /// Checks the profile for settings that don't work well together.
+    #[cfg(feature = "heapless")]
+    pub fn check_consistency(&self) -> heapless::Vec<ConfigWarning, 4> {
+        let mut warnings = heapless::Vec::new();
+
+        let weakened = self.field_weakening.is_some_and(|v| v.percent() > 0);
+        if self
+            .max_assisted_speed
+            .is_some_and(|v| requires_field_weakening(&v))
+            && !weakened
+        {
+            // There are fewer warnings than the capacity, so this can't fail.
+            let _ = warnings.push(ConfigWarning::SpeedRequiresFieldWeakening);
+        }
+
+        if let (Some(mode), Some(speed)) = (self.torque_mode, self.max_assisted_speed) {
+            let above_cap = speed.to_kmh().value > LEGAL_MAX_ASSISTED_SPEED;
+
+            let warning = match (mode.speed_limit, above_cap) {
+                (true, true) => Some(ConfigWarning::SpeedLimitedAboveLegalCap),
+                (false, false) => Some(ConfigWarning::UnlimitedBelowLegalCap),
+                _ => None,
+            };
+            if let Some(warning) = warning {
+                let _ = warnings.push(warning);
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns an iterator over the frames required to apply the profile.
+    ///
+    /// The flash frames are omitted for an empty profile.
+    pub fn frames(&self) -> ProfileFrames<'_> {
+        ProfileFrames {
+            profile: self,
+            step: 0,
+        }
+    }
+}
+
+/// Whether field weakening is required to reach the given speed.
+///
+/// The motor can't go faster than 29 km/h without field weakening.
+pub fn requires_field_weakening(speed: &Speed) -> bool {
+    speed.to_kmh().value > MAX_SPEED_WITHOUT_FIELD_WEAKENING
+}
+
+impl<'a> IntoIterator for &'a Profile {
+    type IntoIter = ProfileFrames<'a>;
+    type Item = Result<[u8; 11], CmdError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.frames()
+    }
+}
+
+impl Iterator for ProfileFrames<'_> {
+    type Item = Result<[u8; 11], CmdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use SettingsWriteCmd::*;
+
+        loop {
+            let cmd = match self.step {
+                0 => self.profile.field_weakening.map(SetFieldWeakening),
+                1 => self.profile.hall_interpolation.map(SetHallInterpolation),
+                2 => self.profile.torque_gain.map(SetTorqueGain),
+                3 => self.profile.max_assisted_speed.map(SetMaxAssistedSpeed),
+                4 => self.profile.torque_mode.map(SetMotorTorqueMode),
+                5 | 6 => (!self.profile.is_empty()).then_some(PERSIST[self.step - 5]),
+                _ => return None,
+            };
+
+            self.step += 1;
+            if let Some(cmd) = cmd {
+                return Some(cmd.try_into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::SpeedUnit;
+
+    fn kmh(value: u8) -> Speed {
+        Speed {
+            value,
+            unit: SpeedUnit::Kmh,
+        }
+    }
+
+    #[test]
+    fn test_profile_frames_are_ordered() {
+        use SettingsWriteCmd::*;
+
+        let profile = Profile {
+            field_weakening: Some(FieldWeakening::new(20).unwrap()),
+            hall_interpolation: Some(HallInterpolation::new(5).unwrap()),
+            torque_gain: Some(TorqueGain::new(0).unwrap()),
+            max_assisted_speed: Some(Default::default()),
+            torque_mode: Some(TorqueMode { speed_limit: true }),
+        };
+
+        let expected = [
+            SetFieldWeakening(FieldWeakening::new(20).unwrap()),
+            SetHallInterpolation(HallInterpolation::new(5).unwrap()),
+            SetTorqueGain(TorqueGain::new(0).unwrap()),
+            SetMaxAssistedSpeed(Default::default()),
+            SetMotorTorqueMode(TorqueMode { speed_limit: true }),
+            WriteFlash,
+            CloseFlash,
+        ];
+
+        let mut count = 0;
+        for (frame, cmd) in (&profile).into_iter().zip(expected) {
+            let expected: [u8; 11] = cmd.try_into().unwrap();
+            assert_eq!(frame.unwrap(), expected);
+            count += 1;
+        }
+
+        assert_eq!(count, expected.len());
+        assert_eq!(profile.frames().count(), expected.len());
+    }
+
+    #[test]
+    fn test_profile_skips_unset_settings() {
+        let profile = Profile {
+            torque_gain: Some(TorqueGain::new(0).unwrap()),
+            ..Default::default()
+        };
+
+        let frame: [u8; 11] = SettingsWriteCmd::SetTorqueGain(TorqueGain::new(0).unwrap())
+            .try_into()
+            .unwrap();
+        assert_eq!(profile.frames().next().unwrap().unwrap(), frame);
+        assert_eq!(profile.frames().count(), 3);
+        assert_eq!(Profile::default().frames().count(), 0);
+    }
+
+    #[test]
+    fn test_requires_field_weakening() {
+        assert!(!requires_field_weakening(&kmh(29)));
+        assert!(requires_field_weakening(&kmh(30)));
+        assert!(requires_field_weakening(&Speed {
+            value: 19,
+            unit: SpeedUnit::Mph,
+        }));
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_check_consistency() {
+        let profile = |speed, weakening: Option<u8>| Profile {
+            max_assisted_speed: Some(kmh(speed)),
+            field_weakening: weakening.map(|v| FieldWeakening::new(v).unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(profile(29, None).check_consistency(), []);
+        assert_eq!(profile(30, Some(20)).check_consistency(), []);
+        assert_eq!(
+            profile(30, None).check_consistency(),
+            [ConfigWarning::SpeedRequiresFieldWeakening]
+        );
+        assert_eq!(
+            profile(30, Some(0)).check_consistency(),
+            [ConfigWarning::SpeedRequiresFieldWeakening]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_check_torque_mode_consistency() {
+        let profile = |speed, speed_limit| Profile {
+            max_assisted_speed: Some(kmh(speed)),
+            field_weakening: Some(FieldWeakening::new(20).unwrap()),
+            torque_mode: Some(TorqueMode { speed_limit }),
+            ..Default::default()
+        };
+
+        assert_eq!(profile(25, true).check_consistency(), []);
+        assert_eq!(profile(32, false).check_consistency(), []);
+        assert_eq!(
+            profile(32, true).check_consistency(),
+            [ConfigWarning::SpeedLimitedAboveLegalCap]
+        );
+        assert_eq!(
+            profile(20, false).check_consistency(),
+            [ConfigWarning::UnlimitedBelowLegalCap]
+        );
+
+        let no_weakening = Profile {
+            field_weakening: None,
+            ..profile(32, true)
+        };
+        assert_eq!(
+            no_weakening.check_consistency(),
+            [
+                ConfigWarning::SpeedRequiresFieldWeakening,
+                ConfigWarning::SpeedLimitedAboveLegalCap
+            ]
+        );
+
+        let no_speed = Profile {
+            max_assisted_speed: None,
+            ..profile(32, true)
+        };
+        assert_eq!(no_speed.check_consistency(), []);
+    }
+}
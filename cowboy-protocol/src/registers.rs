@@ -0,0 +1,368 @@
+use core::ops::RangeInclusive;
+
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+use crate::utils::verify_checksum;
+
+/// Auto lock flag, on device `0xA`.
+pub const AUTO_LOCK: u16 = 0x00;
+
+/// Light flag, on device `0xA`.
+pub const LIGHT: u16 = 0x01;
+
+/// Max assisted speed in km/h, on device `0xA`.
+pub const MAX_ASSISTED_SPEED: u16 = 0x04;
+
+/// Motor torque mode (`1` unlimited, `2` speed-limited), on device `0x1`.
+pub const TORQUE_MODE: u16 = 0x0B;
+
+/// Hall interpolation, on device `0x1`.
+pub const HALL_INTERPOLATION: u16 = 0x80;
+
+/// Field weakening, on device `0x1`.
+pub const FIELD_WEAKENING: u16 = 0x81;
+
+/// Torque gain in Nm, on device `0x1`.
+pub const TORQUE_GAIN: u16 = 0xB3;
+
+/// Flash control, on device `0x1`: `0x7FFF` writes the modified settings to
+/// flash and `0x0` closes it.
+pub const FLASH: u16 = 0x1FF;
+
+/// Registers [`SettingsWriteCmd::read_register`] refuses to read.
+///
+/// These are registers that drive the bike rather than hold a setting, for
+/// which the effect of a read isn't known. Only the flash control is known
+/// so far. `ReadRegister` and `RawRead` can still be built directly to read
+/// them anyway.
+pub const DENIED_READS: [u16; 1] = [FLASH];
+
+/// A register of the bike, as addressed in a frame header.
+///
+/// Registers are ordered by device, then by address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Register {
+    /// Device the register belongs to (`0xA` or `0x1`).
+    pub device: u8,
+
+    /// Address of the register on the device.
+    pub address: u16,
+}
+
+impl Register {
+    /// Get the register addressed by a frame.
+    pub fn of(frame: &[u8; 11]) -> Self {
+        Self {
+            device: frame[0],
+            address: u16::from_be_bytes([frame[2], frame[3]]),
+        }
+    }
+
+    /// Iterate over every register from `start` to `end` inclusive, to scan
+    /// them in bulk.
+    ///
+    /// Both ends must be on the same device: a range spanning devices yields
+    /// nothing.
+    pub fn range(range: RangeInclusive<Register>) -> impl Iterator<Item = Register> {
+        let (start, end) = range.into_inner();
+        let addresses = if start.device == end.device {
+            start.address..=end.address
+        } else {
+            RangeInclusive::new(1, 0)
+        };
+
+        addresses.map(move |address| Register {
+            device: start.device,
+            address,
+        })
+    }
+}
+
+/// Get the register a read response is for, to match it to its read.
+///
+/// Responses are assumed to echo the header of the read, so the register
+/// matches [`SettingsWriteCmd::register`] of the read command. `None` is
+/// returned if the checksum is invalid or the frame isn't a read response.
+///
+/// `ReadRegister` reads put the register to read in the value, so their
+/// responses all address register `0x0`: match those by order instead.
+pub fn correlate(response: &[u8; 11]) -> Option<Register> {
+    if !verify_checksum(response) || response[1] != 0x3 {
+        return None;
+    }
+
+    Some(Register::of(response))
+}
+
+/// Decode the value of `expected` from the response to its read.
+///
+/// Every read response is assumed to echo the read header (device, function
+/// `0x03` and register) and carry the value in bytes 7-8. Responses with an
+/// invalid checksum or for another register are rejected.
+pub fn decode_u16_register(
+    response: &[u8; 11],
+    expected: Register,
+) -> Result<u16, CmdError> {
+    if !verify_checksum(response) {
+        return Err(CmdError::BadChecksum);
+    }
+
+    let [reg_hi, reg_lo] = expected.address.to_be_bytes();
+    let header = [expected.device, 0x3, reg_hi, reg_lo];
+    if let Some(offset) = header.iter().zip(response).position(|(a, b)| a != b) {
+        return Err(CmdError::UnexpectedHeader { offset });
+    }
+
+    Ok(u16::from_be_bytes([response[7], response[8]]))
+}
+
+/// Reads of a contiguous range of registers, to dump them in bulk.
+///
+/// Iterating yields a `ReadRegister` command per register, from `start` to
+/// `end` inclusive. Pair the responses with their registers with
+/// [`RegisterMap::pairs`].
+#[derive(Clone, Debug)]
+pub struct RegisterMap {
+    registers: RangeInclusive<u16>,
+}
+
+impl RegisterMap {
+    /// Creates the reads of the registers from `start` to `end` inclusive.
+    pub fn new(
+        start: u16,
+        end: u16,
+    ) -> Self {
+        Self {
+            registers: start..=end,
+        }
+    }
+
+    /// Decode the value from the response to one of the reads.
+    ///
+    /// `ReadRegister` reads address register `0x0` on device `0x1`, so
+    /// that's the header the response is expected to echo. Nothing in the
+    /// response names the register read: pair values with registers by the
+    /// order the reads were sent, which only holds if the bike answers them
+    /// in order.
+    pub fn decode(response: &[u8; 11]) -> Result<u16, CmdError> {
+        decode_u16_register(
+            response,
+            Register {
+                device: 0x1,
+                address: 0x0,
+            },
+        )
+    }
+
+    /// Pair the registers with the values decoded from the responses to
+    /// their reads.
+    ///
+    /// Responses must be given in the order the reads were sent, and the
+    /// bike is assumed to answer them in that order: see
+    /// [`RegisterMap::decode`]. Pairing stops at the first of the registers
+    /// or responses to run out, and registers already iterated over aren't
+    /// paired: call it on a clone of the map made before sending the reads.
+    pub fn pairs(
+        self,
+        responses: impl IntoIterator<Item = [u8; 11]>,
+    ) -> impl Iterator<Item = Result<(u16, u16), CmdError>> {
+        self.registers
+            .zip(responses)
+            .map(|(register, response)| Ok((register, Self::decode(&response)?)))
+    }
+}
+
+impl Iterator for RegisterMap {
+    type Item = SettingsWriteCmd;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.registers.next().map(SettingsWriteCmd::ReadRegister)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd;
+
+    #[test]
+    fn test_register_constants() {
+        use SettingsWriteCmd::*;
+
+        let register = |cmd: SettingsWriteCmd| {
+            let frame: [u8; 11] = cmd.try_into().unwrap();
+            Register::of(&frame).address
+        };
+
+        assert_eq!(register(SetLight(true)), LIGHT);
+        assert_eq!(register(ReadAutoLock), AUTO_LOCK);
+        assert_eq!(
+            register(SetMaxAssistedSpeed(Default::default())),
+            MAX_ASSISTED_SPEED
+        );
+        assert_eq!(register(ReadMotorTorqueMode), TORQUE_MODE);
+        assert_eq!(register(ReadHallInterpolation), HALL_INTERPOLATION);
+        assert_eq!(
+            register(SetFieldWeakening(Default::default())),
+            FIELD_WEAKENING
+        );
+        assert_eq!(register(ReadTorqueGain), TORQUE_GAIN);
+        assert_eq!(register(WriteFlash), FLASH);
+        assert_eq!(register(CloseFlash), FLASH);
+    }
+
+    #[test]
+    fn test_register_ord() {
+        let register = |device, address| Register { device, address };
+
+        assert!(register(0x1, 0x81) < register(0x1, 0x82));
+        assert!(register(0x1, 0x1FF) < register(0xA, 0x0));
+        assert_eq!(
+            register(0xA, 0x1).max(register(0x1, 0xB3)),
+            register(0xA, 0x1)
+        );
+    }
+
+    #[test]
+    fn test_register_range() {
+        let register = |device, address| Register { device, address };
+
+        let mut range = Register::range(register(0x1, 0x80)..=register(0x1, 0x82));
+        for address in 0x80..=0x82 {
+            assert_eq!(range.next(), Some(register(0x1, address)));
+        }
+        assert_eq!(range.next(), None);
+
+        assert_eq!(
+            Register::range(register(0x1, 0x0)..=register(0x1, u16::MAX)).count(),
+            0x1_0000
+        );
+        assert_eq!(
+            Register::range(register(0xA, 0x4)..=register(0xA, 0x4)).count(),
+            1
+        );
+        assert_eq!(
+            Register::range(register(0xA, 0x4)..=register(0xA, 0x3)).count(),
+            0
+        );
+        assert_eq!(
+            Register::range(register(0x1, 0x0)..=register(0xA, 0x4)).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_correlate() {
+        for cmd in SettingsWriteCmd::all_reads() {
+            let read: [u8; 11] = cmd.try_into().unwrap();
+            let response = cmd!(
+                [read[0], read[1], read[2], read[3], 0x0, 0x1, 0x2, 0x0, 0x0],
+                0x1
+            );
+
+            assert_eq!(correlate(&response), cmd.register(), "{cmd:?}");
+        }
+
+        let response = cmd!([0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 0x1);
+        assert_eq!(
+            correlate(&response),
+            Some(Register {
+                device: 0x1,
+                address: 0x81
+            })
+        );
+
+        let write: [u8; 11] = SettingsWriteCmd::SetLight(true).try_into().unwrap();
+        assert_eq!(correlate(&write), None);
+
+        let mut corrupted = response;
+        corrupted[10] ^= 0xFF;
+        assert_eq!(correlate(&corrupted), None);
+    }
+
+    #[test]
+    fn test_decode_u16_register() {
+        let expected = Register {
+            device: 0x1,
+            address: FIELD_WEAKENING,
+        };
+
+        let response = cmd!([0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 0x0400);
+        assert_eq!(decode_u16_register(&response, expected), Ok(0x0400));
+
+        let mut corrupted = response;
+        corrupted[9] ^= 0xFF;
+        assert_eq!(
+            decode_u16_register(&corrupted, expected),
+            Err(CmdError::BadChecksum)
+        );
+
+        for (body, offset) in [
+            ([0xA, 0x3, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 0),
+            ([0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 1),
+            ([0x1, 0x3, 0x1, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 2),
+            ([0x1, 0x3, 0x0, 0x80, 0x0, 0x1, 0x2, 0x0, 0x0], 3),
+        ] {
+            assert_eq!(
+                decode_u16_register(&cmd!(body, 0x1), expected),
+                Err(CmdError::UnexpectedHeader { offset })
+            );
+        }
+    }
+
+    #[test]
+    fn test_register_map_commands() {
+        let mut map = RegisterMap::new(0x80, 0x82);
+
+        for register in 0x80..=0x82 {
+            assert!(matches!(
+                map.next(),
+                Some(SettingsWriteCmd::ReadRegister(r)) if r == register
+            ));
+        }
+        assert!(map.next().is_none());
+
+        assert_eq!(RegisterMap::new(0x5, 0x5).count(), 1);
+        assert_eq!(RegisterMap::new(0x5, 0x4).count(), 0);
+    }
+
+    #[test]
+    fn test_register_map_decode() {
+        let response = cmd!([0x1, 0x3, 0x0, 0x0, 0x0, 0x1, 0x2, 0x0, 0x0], 0x1234);
+        assert_eq!(RegisterMap::decode(&response), Ok(0x1234));
+
+        let mut corrupted = response;
+        corrupted[10] ^= 0xFF;
+        assert_eq!(RegisterMap::decode(&corrupted), Err(CmdError::BadChecksum));
+
+        let other = cmd!([0xA, 0x3, 0x0, 0x0, 0x0, 0x1, 0x2, 0x0, 0x0], 0x1);
+        assert_eq!(
+            RegisterMap::decode(&other),
+            Err(CmdError::UnexpectedHeader { offset: 0 })
+        );
+
+        let write = cmd!([0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0], 0x1);
+        assert_eq!(
+            RegisterMap::decode(&write),
+            Err(CmdError::UnexpectedHeader { offset: 1 })
+        );
+    }
+
+    #[test]
+    fn test_register_map_pairs() {
+        let response = |value| cmd!([0x1, 0x3, 0x0, 0x0, 0x0, 0x1, 0x2, 0x0, 0x0], value);
+        let mut corrupted = response(0x3);
+        corrupted[10] ^= 0xFF;
+
+        let mut pairs =
+            RegisterMap::new(0x80, 0x82).pairs([response(0x1), corrupted, response(0x3)]);
+        assert_eq!(pairs.next(), Some(Ok((0x80, 0x1))));
+        assert_eq!(pairs.next(), Some(Err(CmdError::BadChecksum)));
+        assert_eq!(pairs.next(), Some(Ok((0x82, 0x3))));
+        assert_eq!(pairs.next(), None);
+
+        let mut pairs = RegisterMap::new(0x80, 0x82).pairs([response(0x1)]);
+        assert_eq!(pairs.next(), Some(Ok((0x80, 0x1))));
+        assert_eq!(pairs.next(), None);
+    }
+}
@@ -0,0 +1,109 @@
+use core::time::Duration;
+
+use crate::mode::CmdMode;
+use crate::transport::Command;
+
+/// How a command should be retried when the bike doesn't respond in time.
+///
+/// This is only policy data: the transport keeps time and resends the
+/// command itself, waiting [`RetryPolicy::backoff`] before each retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of times the command is resent after the first attempt.
+    pub max_retries: u8,
+
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+
+    /// Factor the delay is multiplied by after each retry.
+    pub backoff_factor: u32,
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        initial_backoff: Duration::ZERO,
+        backoff_factor: 1,
+    };
+
+    /// Retry up to 3 times, backing off from 250 ms.
+    pub const DEFAULT: Self = Self {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(250),
+        backoff_factor: 2,
+    };
+
+    /// Get the policy suited to a command.
+    ///
+    /// Only idempotent `WriteWithResponse` commands are retried: a timeout
+    /// is only detectable for them, and resending must be harmless since the
+    /// first write may have landed anyway.
+    pub fn for_command(cmd: &(impl Command + ?Sized)) -> Self {
+        match cmd.mode() {
+            CmdMode::WriteWithResponse if cmd.is_idempotent() => Self::DEFAULT,
+            _ => Self::NONE,
+        }
+    }
+
+    /// Get the delay before the given retry, counting from `1`.
+    ///
+    /// `None` is returned once the retries are exhausted.
+    pub fn backoff(
+        &self,
+        retry: u8,
+    ) -> Option<Duration> {
+        if retry == 0 || retry > self.max_retries {
+            return None;
+        }
+
+        let factor = self.backoff_factor.saturating_pow(retry as u32 - 1);
+        Some(self.initial_backoff.saturating_mul(factor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd::CowboyCharacteristic;
+    use crate::cmd::CowboyLockCmd;
+    use crate::cmd::CowboyService;
+    use crate::cmd::SettingsWriteCmd;
+
+    #[test]
+    fn test_policy_for_command() {
+        use SettingsWriteCmd::*;
+
+        assert_eq!(
+            RetryPolicy::for_command(&SetAutoLock(true)),
+            RetryPolicy::DEFAULT
+        );
+        assert_eq!(RetryPolicy::for_command(&SetLight(true)), RetryPolicy::NONE);
+        assert_eq!(
+            RetryPolicy::for_command(&CowboyService::from(SetAutoLock(true))),
+            RetryPolicy::DEFAULT
+        );
+        assert_eq!(RetryPolicy::for_command(&ReadTorqueGain), RetryPolicy::NONE);
+        assert_eq!(RetryPolicy::for_command(&WriteFlash), RetryPolicy::NONE);
+        assert_eq!(
+            RetryPolicy::for_command(&CowboyService::from(CloseFlash)),
+            RetryPolicy::NONE
+        );
+
+        let lock = CowboyService::Cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::SetLock(true)));
+        assert_eq!(RetryPolicy::for_command(&lock), RetryPolicy::NONE);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::DEFAULT;
+        let ms = Duration::from_millis;
+
+        assert_eq!(policy.backoff(0), None);
+        assert_eq!(policy.backoff(1), Some(ms(250)));
+        assert_eq!(policy.backoff(2), Some(ms(500)));
+        assert_eq!(policy.backoff(3), Some(ms(1000)));
+        assert_eq!(policy.backoff(4), None);
+        assert_eq!(RetryPolicy::NONE.backoff(1), None);
+    }
+}
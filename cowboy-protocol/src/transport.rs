@@ -0,0 +1,376 @@
+#[cfg(feature = "async")]
+use core::future::Future;
+
+use uuid::Uuid;
+
+use crate::cmd::CowboyService;
+use crate::cmd::SettingsCategory;
+use crate::cmd::SettingsCharacteristic;
+use crate::cmd::SettingsWriteCmd;
+use crate::error::CmdError;
+#[cfg(feature = "async")]
+use crate::frame::Frame;
+use crate::mode::CmdMode;
+
+/// A command that can be sent to the bike.
+///
+/// This carries everything a transport needs to send the command: where to
+/// write it, how to write it, and the frame itself. The trait is object
+/// safe, so commands of different types can be queued together as
+/// `dyn Command`.
+pub trait Command {
+    /// Get the service UUID the command is sent to.
+    fn service(&self) -> Uuid;
+
+    /// Get the characteristic UUID the command is written to.
+    fn characteristic(&self) -> Uuid;
+
+    /// Get the write mode of the command.
+    fn mode(&self) -> CmdMode;
+
+    /// Encode the command into a frame.
+    fn to_frame(&self) -> Result<[u8; 11], CmdError>;
+
+    /// Whether sending the command twice has the same effect as sending it
+    /// once.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
+
+// Adding a generic or `Self`-returning method would break `dyn Command`.
+const _: Option<&dyn Command> = None;
+
+/// A BLE transport able to talk to the bike.
+///
+/// This is transport-agnostic: implement it over whichever BLE stack is
+/// available, and [`CowboyTransport::send_command`] takes care of routing
+/// commands to the right characteristic with the right write mode.
+#[cfg(feature = "async")]
+pub trait CowboyTransport {
+    /// Error returned by the transport.
+    type Error: From<CmdError>;
+
+    /// Stream of frames notified by a characteristic.
+    type Notifications: futures_core::Stream<Item = [u8; 11]>;
+
+    /// Write a frame to a characteristic.
+    fn write(
+        &self,
+        service: Uuid,
+        characteristic: Uuid,
+        frame: &[u8; 11],
+        mode: CmdMode,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Subscribe to the notifications of a characteristic.
+    fn subscribe(
+        &self,
+        service: Uuid,
+        characteristic: Uuid,
+    ) -> impl Future<Output = Result<Self::Notifications, Self::Error>>;
+
+    /// Write the frame of a command to its characteristic, with the write
+    /// mode the frame carries.
+    ///
+    /// The frame must carry the write mode of the command, or
+    /// [`CmdError::ModeMismatch`] is returned without writing it.
+    fn write_frame(
+        &self,
+        cmd: &(impl Command + ?Sized),
+        frame: &Frame,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        async move {
+            if frame.mode() != cmd.mode() {
+                return Err(CmdError::ModeMismatch {
+                    expected: cmd.mode(),
+                    got: frame.mode(),
+                }
+                .into());
+            }
+
+            self.write(
+                cmd.service(),
+                cmd.characteristic(),
+                frame.as_bytes(),
+                frame.mode(),
+            )
+            .await
+        }
+    }
+
+    /// Encode a command and write it to its characteristic.
+    fn send_command(
+        &self,
+        cmd: impl Command,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        async move {
+            let frame = Frame::from_command(&cmd)?;
+            self.write_frame(&cmd, &frame).await
+        }
+    }
+}
+
+impl Command for CowboyService {
+    fn service(&self) -> Uuid {
+        CowboyService::service(self)
+    }
+
+    fn characteristic(&self) -> Uuid {
+        CowboyService::characteristic(self)
+    }
+
+    fn mode(&self) -> CmdMode {
+        CowboyService::mode(self)
+    }
+
+    fn to_frame(&self) -> Result<[u8; 11], CmdError> {
+        (*self).try_into()
+    }
+
+    fn is_idempotent(&self) -> bool {
+        match self {
+            CowboyService::Settings(SettingsCharacteristic::Write(cmd)) => cmd.is_idempotent(),
+            _ => true,
+        }
+    }
+}
+
+impl Command for SettingsWriteCmd {
+    fn service(&self) -> Uuid {
+        CowboyService::from(*self).service()
+    }
+
+    fn characteristic(&self) -> Uuid {
+        CowboyService::from(*self).characteristic()
+    }
+
+    fn mode(&self) -> CmdMode {
+        SettingsWriteCmd::mode(self)
+    }
+
+    fn to_frame(&self) -> Result<[u8; 11], CmdError> {
+        (*self).try_into()
+    }
+
+    /// Flash commands open and close a flash write, so repeating one can
+    /// land the bike in a different state than intended.
+    fn is_idempotent(&self) -> bool {
+        self.category() != SettingsCategory::Flash
+    }
+}
+
+impl From<SettingsWriteCmd> for CowboyService {
+    fn from(cmd: SettingsWriteCmd) -> Self {
+        CowboyService::Settings(SettingsCharacteristic::Write(cmd))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_settings_write_cmd_routing() {
+        let cmd = SettingsWriteCmd::SetAutoLock(true);
+        let service = CowboyService::from(cmd);
+
+        assert_eq!(Command::service(&cmd), service.service());
+        assert_eq!(Command::characteristic(&cmd), service.characteristic());
+        assert_eq!(Command::mode(&cmd), CmdMode::WriteWithResponse);
+        assert_eq!(cmd.to_frame(), service.to_frame());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_boxed_commands() {
+        use alloc::boxed::Box;
+        use alloc::vec::Vec;
+
+        use crate::cmd::CowboyCharacteristic;
+        use crate::cmd::CowboyLockCmd;
+        use crate::frame::Frame;
+
+        let lock = CowboyService::Cowboy(CowboyCharacteristic::Lock(CowboyLockCmd::SetLock(true)));
+        let cmds: Vec<Box<dyn Command>> = Vec::from([
+            Box::new(SettingsWriteCmd::SetAutoLock(true)) as Box<dyn Command>,
+            Box::new(lock),
+            Box::new(SettingsWriteCmd::ReadTorqueGain),
+        ]);
+
+        let modes: Vec<_> = cmds.iter().map(|cmd| cmd.mode()).collect();
+        assert_eq!(
+            modes,
+            [
+                CmdMode::WriteWithResponse,
+                CmdMode::WriteOnly,
+                CmdMode::WriteOnly
+            ]
+        );
+
+        assert_eq!(
+            cmds[0].to_frame(),
+            SettingsWriteCmd::SetAutoLock(true).try_into()
+        );
+        assert_eq!(cmds[1].to_frame(), lock.try_into());
+        assert_eq!(cmds[1].service(), lock.service());
+        assert_eq!(
+            Frame::from_command(cmds[2].as_ref()).map(|frame| *frame.as_bytes()),
+            SettingsWriteCmd::ReadTorqueGain.try_into()
+        );
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        use SettingsWriteCmd::*;
+
+        assert!(SetAutoLock(true).is_idempotent());
+        assert!(ReadTorqueGain.is_idempotent());
+        assert!(!WriteFlash.is_idempotent());
+        assert!(!CowboyService::from(CloseFlash).is_idempotent());
+        assert!(CowboyService::from(SetLight(true)).is_idempotent());
+    }
+
+    #[cfg(feature = "async")]
+    mod mock {
+        use core::cell::Cell;
+        use core::pin::pin;
+        use core::pin::Pin;
+        use core::task::Context;
+        use core::task::Poll;
+        use core::task::Waker;
+
+        use futures_core::Stream;
+
+        use super::*;
+
+        /// Service, characteristic, frame and mode of a write.
+        type Write = (Uuid, Uuid, [u8; 11], CmdMode);
+
+        /// Transport that records the last write and notifies canned frames.
+        #[derive(Default)]
+        struct MockTransport {
+            written: Cell<Option<Write>>,
+        }
+
+        struct Notifications([[u8; 11]; 2], usize);
+
+        impl Stream for Notifications {
+            type Item = [u8; 11];
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                let frame = self.0.get(self.1).copied();
+                self.1 += 1;
+                Poll::Ready(frame)
+            }
+        }
+
+        impl CowboyTransport for MockTransport {
+            type Error = CmdError;
+            type Notifications = Notifications;
+
+            async fn write(
+                &self,
+                service: Uuid,
+                characteristic: Uuid,
+                frame: &[u8; 11],
+                mode: CmdMode,
+            ) -> Result<(), Self::Error> {
+                self.written
+                    .set(Some((service, characteristic, *frame, mode)));
+                Ok(())
+            }
+
+            async fn subscribe(
+                &self,
+                _: Uuid,
+                _: Uuid,
+            ) -> Result<Self::Notifications, Self::Error> {
+                Ok(Notifications([[0x1; 11], [0x2; 11]], 0))
+            }
+        }
+
+        /// Poll a future that is expected to be ready immediately.
+        fn now<F: Future>(future: F) -> F::Output {
+            match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("mock future is pending"),
+            }
+        }
+
+        #[test]
+        fn test_send_command() {
+            let transport = MockTransport::default();
+            let cmd = SettingsWriteCmd::SetLight(true);
+
+            now(transport.send_command(cmd)).unwrap();
+            assert_eq!(
+                transport.written.get(),
+                Some((
+                    Command::service(&cmd),
+                    Command::characteristic(&cmd),
+                    cmd.to_frame().unwrap(),
+                    CmdMode::WriteOnly
+                ))
+            );
+
+            let read = CowboyService::from(SettingsWriteCmd::ReadTorqueGain);
+            now(transport.send_command(read)).unwrap();
+            assert_eq!(
+                transport.written.get().map(|(_, _, frame, _)| frame),
+                Some(read.to_frame().unwrap())
+            );
+        }
+
+        #[test]
+        fn test_write_frame_uses_frame_mode() {
+            let transport = MockTransport::default();
+            let cmd = SettingsWriteCmd::SetAutoLock(true);
+            let frame = Frame::from_command(&cmd).unwrap();
+
+            now(transport.write_frame(&cmd, &frame)).unwrap();
+            assert_eq!(
+                transport.written.get().map(|(_, _, _, mode)| mode),
+                Some(CmdMode::WriteWithResponse)
+            );
+        }
+
+        #[test]
+        fn test_write_frame_mode_mismatch() {
+            let transport = MockTransport::default();
+            let cmd = SettingsWriteCmd::SetAutoLock(true);
+            let frame = Frame::new(cmd.to_frame().unwrap(), CmdMode::WriteOnly);
+
+            assert_eq!(
+                now(transport.write_frame(&cmd, &frame)),
+                Err(CmdError::ModeMismatch {
+                    expected: CmdMode::WriteWithResponse,
+                    got: CmdMode::WriteOnly
+                })
+            );
+            assert_eq!(transport.written.get(), None);
+        }
+
+        #[test]
+        fn test_subscribe() {
+            let transport = MockTransport::default();
+            let cmd = SettingsWriteCmd::SetLight(true);
+            let stream = now(transport.subscribe(cmd.service(), cmd.characteristic())).unwrap();
+
+            let mut stream = pin!(stream);
+            let mut cx = Context::from_waker(Waker::noop());
+            assert_eq!(
+                stream.as_mut().poll_next(&mut cx),
+                Poll::Ready(Some([0x1; 11]))
+            );
+            assert_eq!(
+                stream.as_mut().poll_next(&mut cx),
+                Poll::Ready(Some([0x2; 11]))
+            );
+            assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(None));
+        }
+    }
+}
@@ -1,11 +1,25 @@
+use core::fmt;
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::str::FromStr;
+
 use crate::bounded;
+use crate::error::CmdError;
 use crate::error::FieldError;
+use crate::registers::decode_u16_register;
+use crate::registers::Register;
+use crate::registers::FIELD_WEAKENING;
+use crate::registers::HALL_INTERPOLATION;
+use crate::registers::MAX_ASSISTED_SPEED;
+use crate::registers::TORQUE_GAIN;
+use crate::registers::TORQUE_MODE;
+use crate::utils::value_u8;
 
-const MAX_FIELD_WEAKENING: u8 = 0x64;
-const MAX_HALL_INTERPOLATION: u8 = 0x19;
-const ASI_FIELD_WEAKENING_MULTIPLIER: f32 = 40.96;
+const MAX_FIELD_WEAKENING_FOR_BIKE: u16 = 0x1000;
+/// Length of a mile in millionths of a kilometer.
+const MILE_IN_MICRO_KM: u32 = 1_609_344;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct FieldWeakening {
     weakening: u8,
 }
@@ -14,7 +28,7 @@ pub struct FieldWeakening {
 ///
 /// This adjust how **quickly** the motor is giving boost
 /// when you just start pedalling.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct HallInterpolation {
     interpolation: u8,
 }
@@ -26,30 +40,45 @@ pub struct Speed {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum SpeedUnit {
     #[default]
     Kmh,
+    Mph,
 }
 
-/// Toreque gain configuration.
-#[derive(Clone, Copy, Debug, Default)]
+/// Torque gain configuration.
+///
+/// There is no `Default`: the factory gain isn't known, and a gain of 0 Nm
+/// means no assistance at all, so the gain must always be chosen.
+///
+/// ```compile_fail
+/// let gain = cowboy_protocol::types::TorqueGain::default();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct TorqueGain {
-    pub gain: u8,
-    pub unit: TorqueGainUnit,
+    gain: u8,
+    unit: TorqueGainUnit,
 }
 
 /// Torque gain unit.
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum TorqueGainUnit {
     /// Newton meters
     #[default]
     Nm,
 }
 
+/// Lock state of the bike.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LockState {
+    Unlocked,
+    Locked,
+}
+
 /// Torque mode configuration.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct TorqueMode {
     /// Should there be a speed limit?
     ///
@@ -59,39 +88,74 @@ pub struct TorqueMode {
 }
 
 impl FieldWeakening {
+    /// Highest field weakening accepted, in percent.
+    pub const MAX: u8 = 0x64;
+
     /// Set the field weakening value in percentage.
     ///
-    /// The value must be between 0 and 100.
+    /// The value must be between 0 and [`FieldWeakening::MAX`].
     pub fn new(weakening: u8) -> Result<Self, FieldError> {
-        bounded!(weakening, MAX_FIELD_WEAKENING);
+        bounded!("field_weakening", weakening, Self::MAX);
         Ok(Self { weakening })
     }
 
     /// Set the field weakening from the weakening value read from the bike.
+    ///
+    /// Values above the register's max (4096) are clamped to 100%.
     pub fn new_from_bike(weakening: u16) -> Self {
+        // Integer math keeps this deterministic and free of soft-float:
+        // `weakening / 40.96` is `weakening * 100 / 4096`, rounded up.
+        let weakening = weakening.min(MAX_FIELD_WEAKENING_FOR_BIKE) as u32;
+        let scale = MAX_FIELD_WEAKENING_FOR_BIKE as u32;
+
         Self {
-            weakening: (weakening as f32 / ASI_FIELD_WEAKENING_MULTIPLIER).ceil() as u8,
+            weakening: ((weakening * Self::MAX as u32).div_ceil(scale)) as u8,
         }
     }
 
     /// Returns the field weakening value in percentage.
+    #[deprecated(note = "use `percent` instead")]
     pub fn weakening(&self) -> u8 {
+        self.percent()
+    }
+
+    /// Returns the field weakening value in percentage.
+    pub fn percent(&self) -> u8 {
+        self.weakening
+    }
+
+    /// Returns the field weakening value in percentage.
+    pub fn value(&self) -> u8 {
         self.weakening
     }
 
     /// Returns the field weakening value to be sent to the bike.
+    #[deprecated(note = "use `raw_bike_value` instead")]
     pub fn weakening_for_bike(&self) -> u16 {
-        (self.weakening as f32 * ASI_FIELD_WEAKENING_MULTIPLIER).floor() as u16
+        self.raw_bike_value()
+    }
+
+    /// Returns the field weakening on the bike's scale, from 0 to 4096 (100%).
+    ///
+    /// This is only a conversion: frames carry [`FieldWeakening::percent`].
+    pub fn raw_bike_value(&self) -> u16 {
+        // `weakening * 40.96` is `weakening * 4096 / 100`, rounded down.
+        let weakening =
+            self.weakening as u32 * MAX_FIELD_WEAKENING_FOR_BIKE as u32 / Self::MAX as u32;
+        weakening.min(MAX_FIELD_WEAKENING_FOR_BIKE as u32) as u16
     }
 }
 
 impl HallInterpolation {
+    /// Highest hall interpolation accepted.
+    pub const MAX: u8 = 0x19;
+
     /// Creates a new hall interpolation configuration.
     ///
-    /// The interpolation is a value between 0 and 25.
+    /// The interpolation is a value between 0 and [`HallInterpolation::MAX`].
     /// Any error is returned if the value is out of range.
     pub fn new(interpolation: u8) -> Result<Self, FieldError> {
-        bounded!(interpolation, MAX_HALL_INTERPOLATION);
+        bounded!("hall_interpolation", interpolation, Self::MAX);
         Ok(Self { interpolation })
     }
 
@@ -99,6 +163,138 @@ impl HallInterpolation {
     pub fn interpolation(&self) -> u8 {
         self.interpolation
     }
+
+    /// Returns the hall interpolation value.
+    pub fn value(&self) -> u8 {
+        self.interpolation
+    }
+}
+
+impl TorqueGain {
+    /// Highest torque gain accepted, in Nm.
+    ///
+    /// This is the rated peak torque of the motor: asking for more could
+    /// drive it past what it is designed for.
+    pub const MAX: u8 = 45;
+
+    /// Creates a new torque gain configuration, in Nm.
+    ///
+    /// The gain must be between 0 and [`TorqueGain::MAX`].
+    pub fn new(gain: u8) -> Result<Self, FieldError> {
+        bounded!("torque_gain", gain, Self::MAX);
+        Ok(Self {
+            gain,
+            unit: TorqueGainUnit::Nm,
+        })
+    }
+
+    /// Returns the torque gain value.
+    pub fn value(&self) -> u8 {
+        self.gain
+    }
+
+    /// Returns the unit of the torque gain.
+    pub fn unit(&self) -> TorqueGainUnit {
+        self.unit
+    }
+}
+
+impl TryFrom<u8> for LockState {
+    type Error = FieldError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(Self::Unlocked),
+            0x1 => Ok(Self::Locked),
+            got => Err(FieldError::InvalidVariant { got }),
+        }
+    }
+}
+
+impl Speed {
+    /// Highest max assisted speed accepted.
+    pub const MAX: Self = Self {
+        value: 0x3C,
+        unit: SpeedUnit::Kmh,
+    };
+
+    /// Creates a new speed.
+    ///
+    /// The speed must be between 0 and [`Speed::MAX`] (60 km/h), or the
+    /// equivalent in the given unit (37 mph).
+    pub fn new(
+        value: u8,
+        unit: SpeedUnit,
+    ) -> Result<Self, FieldError> {
+        let max = Self::MAX;
+
+        match unit {
+            SpeedUnit::Kmh => bounded!("max_assisted_speed", value, max.value),
+            SpeedUnit::Mph => bounded!("max_assisted_speed", value, max.to_mph().value),
+        }
+
+        Ok(Self { value, unit })
+    }
+
+    /// Creates a new speed in km/h.
+    pub fn kmh(value: u8) -> Result<Self, FieldError> {
+        Self::new(value, SpeedUnit::Kmh)
+    }
+
+    /// Creates a new speed in mph.
+    pub fn mph(value: u8) -> Result<Self, FieldError> {
+        Self::new(value, SpeedUnit::Mph)
+    }
+
+    /// Returns the speed converted to km/h.
+    ///
+    /// The value is rounded to the nearest integer and saturates at `u8::MAX`.
+    pub fn to_kmh(&self) -> Self {
+        let value = match self.unit {
+            SpeedUnit::Kmh => self.value,
+            SpeedUnit::Mph => {
+                let kmh = (self.value as u32 * MILE_IN_MICRO_KM + 500_000) / 1_000_000;
+                kmh.min(u8::MAX as u32) as u8
+            }
+        };
+
+        Self {
+            value,
+            unit: SpeedUnit::Kmh,
+        }
+    }
+
+    /// Returns the speed converted to mph.
+    ///
+    /// The value is rounded to the nearest integer. The rounding error is at
+    /// most 0.5 mph (~0.8 km/h), so converting km/h to mph and back with
+    /// [`Speed::to_kmh`] never drifts by more than 1 km/h.
+    pub fn to_mph(&self) -> Self {
+        let value = match self.unit {
+            SpeedUnit::Mph => self.value,
+            SpeedUnit::Kmh => {
+                ((self.value as u32 * 1_000_000 + MILE_IN_MICRO_KM / 2) / MILE_IN_MICRO_KM) as u8
+            }
+        };
+
+        Self {
+            value,
+            unit: SpeedUnit::Mph,
+        }
+    }
+}
+
+impl TryFrom<u8> for TorqueMode {
+    type Error = FieldError;
+
+    /// Decode the torque mode from the value read from the bike.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x1 => Ok(Self { speed_limit: false }),
+            0x2 => Ok(Self { speed_limit: true }),
+            got => Err(FieldError::InvalidVariant { got }),
+        }
+    }
 }
 
 impl Default for Speed {
@@ -109,3 +305,700 @@ impl Default for Speed {
         }
     }
 }
+
+/// Speeds are compared in km/h, so speeds in different units are equal when
+/// they round to the same whole km/h (e.g. 15 mph and 24 km/h).
+impl PartialEq for Speed {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.to_kmh().value == other.to_kmh().value
+    }
+}
+
+impl Eq for Speed {}
+
+impl Hash for Speed {
+    fn hash<H: Hasher>(
+        &self,
+        state: &mut H,
+    ) {
+        self.to_kmh().value.hash(state);
+    }
+}
+
+impl fmt::Display for FieldWeakening {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}%", self.weakening)
+    }
+}
+
+impl fmt::Display for HallInterpolation {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.interpolation)
+    }
+}
+
+impl fmt::Display for TorqueGain {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{} {}", self.gain, self.unit)
+    }
+}
+
+impl fmt::Display for TorqueMode {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self.speed_limit {
+            true => f.write_str("speed-limited"),
+            false => f.write_str("unlimited"),
+        }
+    }
+}
+
+impl FieldWeakening {
+    /// Decode the response to `ReadFieldWeakening`.
+    pub fn from_response(response: &[u8; 11]) -> Result<Self, CmdError> {
+        let value = decode_u16_register(response, register(0x1, FIELD_WEAKENING))?;
+        Ok(Self::new(value_u8(value)?)?)
+    }
+}
+
+impl HallInterpolation {
+    /// Decode the response to `ReadHallInterpolation`.
+    pub fn from_response(response: &[u8; 11]) -> Result<Self, CmdError> {
+        let value = decode_u16_register(response, register(0x1, HALL_INTERPOLATION))?;
+        Ok(Self::new(value_u8(value)?)?)
+    }
+}
+
+impl TorqueGain {
+    /// Decode the response to `ReadTorqueGain`.
+    pub fn from_response(response: &[u8; 11]) -> Result<Self, CmdError> {
+        let value = decode_u16_register(response, register(0x1, TORQUE_GAIN))?;
+        Ok(Self::new(value_u8(value)?)?)
+    }
+}
+
+impl TorqueMode {
+    /// Decode the response to `ReadMotorTorqueMode`.
+    pub fn from_response(response: &[u8; 11]) -> Result<Self, CmdError> {
+        let value = decode_u16_register(response, register(0x1, TORQUE_MODE))?;
+        Ok(value_u8(value)?.try_into()?)
+    }
+}
+
+impl Speed {
+    /// Decode the response to `ReadMaxAssistedSpeed`, in km/h.
+    pub fn from_response(response: &[u8; 11]) -> Result<Self, CmdError> {
+        let value = decode_u16_register(response, register(0xA, MAX_ASSISTED_SPEED))?;
+        Ok(Self::new(value_u8(value)?, SpeedUnit::Kmh)?)
+    }
+}
+
+fn register(
+    device: u8,
+    address: u16,
+) -> Register {
+    Register { device, address }
+}
+
+/// Implement `render` for types whose `Display` output never exceeds `len`
+/// bytes.
+#[cfg(feature = "heapless")]
+macro_rules! impl_render {
+    ($($ty: ty => $len: literal),* $(,)?) => {
+        $(
+            impl $ty {
+                /// Render the value as its `Display` output, without `alloc`.
+                pub fn render(&self) -> heapless::String<$len> {
+                    use core::fmt::Write;
+
+                    let mut out = heapless::String::new();
+                    // The capacity fits the longest output, so this can't fail.
+                    let _ = write!(out, "{self}");
+                    out
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "heapless")]
+impl_render! {
+    FieldWeakening => 4,
+    HallInterpolation => 3,
+    TorqueGain => 6,
+    TorqueMode => 13,
+}
+
+impl SpeedUnit {
+    /// Returns the symbol of the unit, as accepted by [`SpeedUnit::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Kmh => "km/h",
+            Self::Mph => "mph",
+        }
+    }
+}
+
+impl TorqueGainUnit {
+    /// Returns the symbol of the unit, as accepted by
+    /// [`TorqueGainUnit::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nm => "Nm",
+        }
+    }
+}
+
+impl fmt::Display for SpeedUnit {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for TorqueGainUnit {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses a unit symbol, ignoring case (e.g. `km/h` or `MPH`).
+impl FromStr for SpeedUnit {
+    type Err = FieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Kmh, Self::Mph]
+            .into_iter()
+            .find(|unit| unit.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or(FieldError::UnknownUnit)
+    }
+}
+
+/// Parses a unit symbol, ignoring case (e.g. `Nm`).
+impl FromStr for TorqueGainUnit {
+    type Err = FieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Nm]
+            .into_iter()
+            .find(|unit| unit.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or(FieldError::UnknownUnit)
+    }
+}
+
+/// Parses a percentage, with or without a `%` suffix (e.g. `15%`).
+impl FromStr for FieldWeakening {
+    type Err = FieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(parse_value(s, "%")?)
+    }
+}
+
+/// Parses the raw interpolation value (e.g. `20`).
+impl FromStr for HallInterpolation {
+    type Err = FieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(parse_value(s, "")?)
+    }
+}
+
+/// Parses a gain in Nm, with or without the unit (e.g. `5 Nm`).
+impl FromStr for TorqueGain {
+    type Err = FieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(parse_value(s, "Nm")?)
+    }
+}
+
+/// Parse a `u8`, optionally followed by `unit`.
+fn parse_value(
+    s: &str,
+    unit: &str,
+) -> Result<u8, FieldError> {
+    let s = s.trim();
+    let s = match unit.is_empty() {
+        true => s,
+        false => s.strip_suffix(unit).unwrap_or(s).trim_end(),
+    };
+
+    s.parse().map_err(|_| FieldError::InvalidFormat)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_speed_conversion() {
+        let kmh = |value| Speed {
+            value,
+            unit: SpeedUnit::Kmh,
+        };
+        let mph = |value| Speed {
+            value,
+            unit: SpeedUnit::Mph,
+        };
+
+        assert_eq!(kmh(25).to_mph().value, 16);
+        assert_eq!(kmh(25).to_kmh().value, 25);
+        assert_eq!(mph(15).to_kmh().value, 24);
+        assert_eq!(mph(15).to_mph().value, 15);
+        assert_eq!(mph(u8::MAX).to_kmh().value, u8::MAX);
+    }
+
+    /// The conversions as they were done with floats.
+    const ASI_FIELD_WEAKENING_MULTIPLIER: f32 = 40.96;
+
+    fn float_from_bike(weakening: u16) -> u8 {
+        let weakening =
+            weakening.min(MAX_FIELD_WEAKENING_FOR_BIKE) as f32 / ASI_FIELD_WEAKENING_MULTIPLIER;
+        let truncated = weakening as u8;
+        truncated.saturating_add(((truncated as f32) < weakening) as u8)
+    }
+
+    fn float_for_bike(weakening: u8) -> u16 {
+        let weakening = (weakening as f32 * ASI_FIELD_WEAKENING_MULTIPLIER) as u16;
+        weakening.min(MAX_FIELD_WEAKENING_FOR_BIKE)
+    }
+
+    #[test]
+    fn test_field_weakening_matches_float() {
+        for percent in 0..=FieldWeakening::MAX {
+            let weakening = FieldWeakening::new(percent).unwrap();
+            assert_eq!(
+                weakening.raw_bike_value(),
+                float_for_bike(percent),
+                "{percent}%"
+            );
+        }
+
+        for value in 0..=u16::MAX {
+            assert_eq!(
+                FieldWeakening::new_from_bike(value).percent(),
+                float_from_bike(value),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_field_weakening_deprecated_names() {
+        for percent in 0..=FieldWeakening::MAX {
+            let weakening = FieldWeakening::new(percent).unwrap();
+            assert_eq!(weakening.weakening(), weakening.percent());
+            assert_eq!(weakening.weakening_for_bike(), weakening.raw_bike_value());
+        }
+    }
+
+    #[test]
+    fn test_field_weakening_top_of_range() {
+        assert_eq!(FieldWeakening::new(99).unwrap().raw_bike_value(), 4055);
+        assert_eq!(FieldWeakening::new(100).unwrap().raw_bike_value(), 4096);
+
+        assert_eq!(FieldWeakening::new_from_bike(4096).percent(), 100);
+        assert_eq!(FieldWeakening::new_from_bike(4097).percent(), 100);
+        assert_eq!(FieldWeakening::new_from_bike(u16::MAX).percent(), 100);
+        assert_eq!(
+            FieldWeakening::new_from_bike(u16::MAX).raw_bike_value(),
+            4096
+        );
+
+        let unchecked = FieldWeakening { weakening: u8::MAX };
+        assert_eq!(unchecked.raw_bike_value(), 4096);
+    }
+
+    #[test]
+    fn test_torque_gain_ceiling() {
+        assert_eq!(TorqueGain::new(45).map(|v| v.value()), Ok(45));
+        assert_eq!(
+            TorqueGain::new(46).err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 45,
+                field: Some("torque_gain"),
+            })
+        );
+        assert_eq!(
+            TorqueGain::new(u8::MAX).err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 45,
+                field: Some("torque_gain"),
+            })
+        );
+        assert_eq!(
+            "46 Nm".parse::<TorqueGain>().err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 45,
+                field: Some("torque_gain"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_max_constants_match_validation() {
+        assert!(FieldWeakening::new(FieldWeakening::MAX).is_ok());
+        assert!(FieldWeakening::new(FieldWeakening::MAX + 1).is_err());
+        assert!(HallInterpolation::new(HallInterpolation::MAX).is_ok());
+        assert!(HallInterpolation::new(HallInterpolation::MAX + 1).is_err());
+        assert!(TorqueGain::new(TorqueGain::MAX).is_ok());
+        assert!(TorqueGain::new(TorqueGain::MAX + 1).is_err());
+        assert!(Speed::new(Speed::MAX.value, Speed::MAX.unit).is_ok());
+        assert!(Speed::kmh(Speed::MAX.value + 1).is_err());
+        assert!(Speed::mph(Speed::MAX.to_mph().value).is_ok());
+        assert!(Speed::mph(Speed::MAX.to_mph().value + 1).is_err());
+
+        assert_eq!(
+            (
+                FieldWeakening::MAX,
+                HallInterpolation::MAX,
+                Speed::MAX.value
+            ),
+            (100, 25, 60)
+        );
+    }
+
+    #[test]
+    fn test_invalid_range_field() {
+        let field = |err: Option<FieldError>| match err {
+            Some(FieldError::InvalidRange { field, .. }) => field,
+            _ => None,
+        };
+
+        assert_eq!(
+            field(FieldWeakening::new(101).err()),
+            Some("field_weakening")
+        );
+        assert_eq!(
+            field(HallInterpolation::new(26).err()),
+            Some("hall_interpolation")
+        );
+        assert_eq!(field(TorqueGain::new(46).err()), Some("torque_gain"));
+        assert_eq!(field(Speed::kmh(61).err()), Some("max_assisted_speed"));
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        assert_eq!(FieldWeakening::new(20).unwrap().value(), 20);
+        assert_eq!(HallInterpolation::new(12).unwrap().value(), 12);
+        assert_eq!(TorqueGain::new(30).unwrap().value(), 30);
+        assert!(matches!(
+            TorqueGain::new(30).unwrap().unit(),
+            TorqueGainUnit::Nm
+        ));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("15%".parse::<FieldWeakening>().map(|v| v.value()), Ok(15));
+        assert_eq!("15".parse::<FieldWeakening>().map(|v| v.value()), Ok(15));
+        assert_eq!(
+            " 15 % ".parse::<FieldWeakening>().map(|v| v.value()),
+            Ok(15)
+        );
+        assert_eq!("20".parse::<HallInterpolation>().map(|v| v.value()), Ok(20));
+        assert_eq!("5 Nm".parse::<TorqueGain>().map(|v| v.value()), Ok(5));
+        assert_eq!("5Nm".parse::<TorqueGain>().map(|v| v.value()), Ok(5));
+
+        assert_eq!(
+            "101%".parse::<FieldWeakening>().err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 100,
+                field: Some("field_weakening"),
+            })
+        );
+        assert_eq!(
+            "15 Nm".parse::<FieldWeakening>().err(),
+            Some(FieldError::InvalidFormat)
+        );
+        assert_eq!(
+            "20%".parse::<HallInterpolation>().err(),
+            Some(FieldError::InvalidFormat)
+        );
+        assert_eq!(
+            "".parse::<TorqueGain>().err(),
+            Some(FieldError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_display() {
+        use core::fmt::Write;
+
+        let mut out = heapless::String::<64>::new();
+        write!(
+            out,
+            "{}, {}, {}, {}, {}",
+            FieldWeakening::new(15).unwrap(),
+            HallInterpolation::new(20).unwrap(),
+            TorqueGain::new(5).unwrap(),
+            TorqueMode { speed_limit: true },
+            TorqueMode { speed_limit: false },
+        )
+        .unwrap();
+        assert_eq!(out, "15%, 20, 5 Nm, speed-limited, unlimited");
+    }
+
+    #[test]
+    fn test_from_response() {
+        use crate::cmd;
+
+        let response = |device, register: u16, value| {
+            let [reg_hi, reg_lo] = register.to_be_bytes();
+            cmd!(
+                [device, 0x3, reg_hi, reg_lo, 0x0, 0x1, 0x2, 0x0, 0x0],
+                value
+            )
+        };
+
+        assert_eq!(
+            FieldWeakening::from_response(&response(0x1, FIELD_WEAKENING, 25)),
+            FieldWeakening::new(25).map_err(CmdError::from)
+        );
+        assert_eq!(
+            HallInterpolation::from_response(&response(0x1, HALL_INTERPOLATION, 20)),
+            HallInterpolation::new(20).map_err(CmdError::from)
+        );
+        assert_eq!(
+            TorqueGain::from_response(&response(0x1, TORQUE_GAIN, 5)),
+            TorqueGain::new(5).map_err(CmdError::from)
+        );
+        assert_eq!(
+            TorqueMode::from_response(&response(0x1, TORQUE_MODE, 0x2)),
+            Ok(TorqueMode { speed_limit: true })
+        );
+        assert_eq!(
+            Speed::from_response(&response(0xA, MAX_ASSISTED_SPEED, 25)),
+            Ok(Speed::default())
+        );
+
+        assert_eq!(
+            TorqueGain::from_response(&response(0x1, TORQUE_GAIN, 0x100)),
+            Err(CmdError::InvalidField(FieldError::InvalidRange {
+                start: 0,
+                end: u8::MAX,
+                field: None,
+            }))
+        );
+        assert_eq!(
+            TorqueMode::from_response(&response(0x1, TORQUE_MODE, 0x3)),
+            Err(CmdError::InvalidField(FieldError::InvalidVariant {
+                got: 0x3
+            }))
+        );
+        assert_eq!(
+            TorqueGain::from_response(&response(0x1, TORQUE_MODE, 5)),
+            Err(CmdError::UnexpectedHeader { offset: 3 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_render_matches_display() {
+        use core::fmt::Write;
+
+        fn display(value: impl fmt::Display) -> heapless::String<16> {
+            let mut out = heapless::String::new();
+            write!(out, "{value}").unwrap();
+            out
+        }
+
+        for value in [0, 15, FieldWeakening::MAX] {
+            let v = FieldWeakening::new(value).unwrap();
+            assert_eq!(v.render(), display(v));
+        }
+        for value in [0, 20, HallInterpolation::MAX] {
+            let v = HallInterpolation::new(value).unwrap();
+            assert_eq!(v.render(), display(v));
+        }
+        for value in [0, 5, TorqueGain::MAX] {
+            let v = TorqueGain::new(value).unwrap();
+            assert_eq!(v.render(), display(v));
+        }
+        for speed_limit in [true, false] {
+            let v = TorqueMode { speed_limit };
+            assert_eq!(v.render(), display(v));
+        }
+
+        // Unchecked values must still fit.
+        let v = FieldWeakening { weakening: u8::MAX };
+        assert_eq!(v.render(), display(v));
+        let v = HallInterpolation {
+            interpolation: u8::MAX,
+        };
+        assert_eq!(v.render(), display(v));
+        let v = TorqueGain {
+            gain: u8::MAX,
+            unit: TorqueGainUnit::Nm,
+        };
+        assert_eq!(v.render(), display(v));
+    }
+
+    #[test]
+    fn test_unit_from_str() {
+        assert_eq!("km/h".parse(), Ok(SpeedUnit::Kmh));
+        assert_eq!("KM/H".parse(), Ok(SpeedUnit::Kmh));
+        assert_eq!(" mph ".parse(), Ok(SpeedUnit::Mph));
+        assert_eq!("Mph".parse(), Ok(SpeedUnit::Mph));
+        assert_eq!("nm".parse(), Ok(TorqueGainUnit::Nm));
+
+        assert_eq!("m/s".parse::<SpeedUnit>(), Err(FieldError::UnknownUnit));
+        assert_eq!("".parse::<SpeedUnit>(), Err(FieldError::UnknownUnit));
+        assert_eq!("Nm".parse::<SpeedUnit>(), Err(FieldError::UnknownUnit));
+        assert_eq!(
+            "lbft".parse::<TorqueGainUnit>(),
+            Err(FieldError::UnknownUnit)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_unit_display_from_str_round_trip() {
+        use core::fmt::Write;
+
+        let mut out = heapless::String::<8>::new();
+        for unit in [SpeedUnit::Kmh, SpeedUnit::Mph] {
+            out.clear();
+            write!(out, "{unit}").unwrap();
+            assert_eq!(out.parse(), Ok(unit));
+        }
+
+        out.clear();
+        write!(out, "{}", TorqueGainUnit::Nm).unwrap();
+        assert_eq!(out, "Nm");
+        assert_eq!(out.parse(), Ok(TorqueGainUnit::Nm));
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_display_from_str_round_trip() {
+        use core::fmt::Write;
+
+        let mut out = heapless::String::<16>::new();
+        for value in [0, 15, 100] {
+            out.clear();
+            write!(out, "{}", FieldWeakening::new(value).unwrap()).unwrap();
+            assert_eq!(out.parse::<FieldWeakening>().map(|v| v.value()), Ok(value));
+        }
+
+        for value in [0, 20, 25] {
+            out.clear();
+            write!(out, "{}", HallInterpolation::new(value).unwrap()).unwrap();
+            assert_eq!(
+                out.parse::<HallInterpolation>().map(|v| v.value()),
+                Ok(value)
+            );
+        }
+
+        for value in [0, 5, TorqueGain::MAX] {
+            out.clear();
+            write!(out, "{}", TorqueGain::new(value).unwrap()).unwrap();
+            assert_eq!(out.parse::<TorqueGain>().map(|v| v.value()), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_speed_constructors() {
+        let kmh = Speed::kmh(25).unwrap();
+        assert_eq!(kmh.value, 25);
+        assert_eq!(kmh.unit, SpeedUnit::Kmh);
+
+        let mph = Speed::mph(15).unwrap();
+        assert_eq!(mph.value, 15);
+        assert_eq!(mph.unit, SpeedUnit::Mph);
+
+        assert!(Speed::kmh(60).is_ok());
+        assert!(Speed::mph(37).is_ok());
+        assert_eq!(
+            Speed::kmh(61).err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 60,
+                field: Some("max_assisted_speed"),
+            })
+        );
+        assert_eq!(
+            Speed::mph(38).err(),
+            Some(FieldError::InvalidRange {
+                start: 0,
+                end: 37,
+                field: Some("max_assisted_speed"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_speed_eq_across_units() {
+        assert_eq!(Speed::kmh(24).unwrap(), Speed::mph(15).unwrap());
+        assert_eq!(Speed::kmh(25).unwrap(), Speed::kmh(25).unwrap());
+        assert_eq!(Speed::kmh(16).unwrap(), Speed::mph(10).unwrap());
+
+        // 15 mph is 24.14 km/h, so it doesn't round to 25 km/h.
+        assert_ne!(Speed::kmh(25).unwrap(), Speed::mph(15).unwrap());
+        assert_ne!(Speed::kmh(23).unwrap(), Speed::mph(15).unwrap());
+        assert_ne!(Speed::mph(15).unwrap(), Speed::mph(16).unwrap());
+    }
+
+    #[test]
+    fn test_speed_round_trip_drift() {
+        for value in 0..=60 {
+            let speed = Speed {
+                value,
+                unit: SpeedUnit::Kmh,
+            };
+
+            let round_trip = speed.to_mph().to_kmh();
+            assert!(round_trip.value.abs_diff(value) <= 1, "{value} km/h");
+        }
+    }
+
+    #[test]
+    fn test_decode_variants() {
+        assert_eq!(LockState::try_from(0x0), Ok(LockState::Unlocked));
+        assert_eq!(LockState::try_from(0x1), Ok(LockState::Locked));
+        assert!(!TorqueMode::try_from(0x1).unwrap().speed_limit);
+        assert!(TorqueMode::try_from(0x2).unwrap().speed_limit);
+    }
+
+    #[test]
+    fn test_decode_unknown_variants() {
+        use FieldError::InvalidVariant;
+
+        assert_eq!(LockState::try_from(0x2), Err(InvalidVariant { got: 0x2 }));
+        assert_eq!(LockState::try_from(0xFF), Err(InvalidVariant { got: 0xFF }));
+        assert_eq!(
+            TorqueMode::try_from(0x0).err(),
+            Some(InvalidVariant { got: 0x0 })
+        );
+        assert_eq!(
+            TorqueMode::try_from(0x3).err(),
+            Some(InvalidVariant { got: 0x3 })
+        );
+    }
+}
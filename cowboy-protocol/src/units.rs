@@ -0,0 +1,78 @@
+use core::fmt;
+
+/// A distance in meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Meters(pub u32);
+
+/// A duration in seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Seconds(pub u32);
+
+/// An amount of energy in watt-hours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WattHours(pub u16);
+
+impl Meters {
+    /// Returns the distance in tenths of a kilometer, rounded down.
+    pub fn as_km_tenths(&self) -> u32 {
+        self.0 / 100
+    }
+}
+
+impl Seconds {
+    /// Returns the duration in whole minutes, rounded down.
+    pub fn as_minutes(&self) -> u32 {
+        self.0 / 60
+    }
+}
+
+impl fmt::Display for Meters {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{} m", self.0)
+    }
+}
+
+impl fmt::Display for Seconds {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{} s", self.0)
+    }
+}
+
+impl fmt::Display for WattHours {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{} Wh", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Meters(0).as_km_tenths(), 0);
+        assert_eq!(Meters(99).as_km_tenths(), 0);
+        assert_eq!(Meters(12_345).as_km_tenths(), 123);
+        assert_eq!(Seconds(59).as_minutes(), 0);
+        assert_eq!(Seconds(3_600).as_minutes(), 60);
+    }
+
+    #[test]
+    #[cfg(feature = "heapless")]
+    fn test_display() {
+        use core::fmt::Write;
+
+        let mut out = heapless::String::<32>::new();
+        write!(out, "{}, {}, {}", Meters(1_200), Seconds(90), WattHours(12)).unwrap();
+        assert_eq!(out, "1200 m, 90 s, 12 Wh");
+    }
+}
@@ -1,43 +1,244 @@
+use crate::error::CmdError;
+use crate::error::FieldError;
+use crate::frame::Checksum;
+use crate::frame::ChecksumAlgorithm;
+
 #[macro_export]
 macro_rules! cmd {
     ($command: expr, $value: expr) => {
-        crate::utils::packetize(&crate::utils::write_value($command, $value))
+        $crate::utils::packetize(&$crate::utils::write_value($command, $value))
     };
     ($command: expr) => {
-        crate::utils::packetize(&$command)
+        $crate::utils::packetize(&$command)
     };
 }
 
 #[macro_export]
 macro_rules! bounded {
-    ($value: expr, $max: expr) => {
-        bounded!($value, 0, $max)
+    ($field: literal, $value: expr, $max: expr) => {
+        bounded!($field, $value, 0, $max)
     };
-    ($value: expr, $min: expr, $max: expr) => {
+    ($field: literal, $value: expr, $min: expr, $max: expr) => {
         if $value > $max {
             return Err(FieldError::InvalidRange {
                 start: $min,
                 end: $max,
+                field: Some($field),
             });
         }
     };
 }
 
+/// Length of a command body, without its checksum.
+pub(super) const BODY_LEN: usize = 9;
+
+/// Length of the checksum appended to a body.
+pub(super) const CHECKSUM_LEN: usize = 2;
+
+/// Length of a frame ready for transmission.
+pub(super) const FRAME_LEN: usize = 11;
+
+// A frame is a body immediately followed by its checksum.
+const _: () = assert!(BODY_LEN + CHECKSUM_LEN == FRAME_LEN);
+
 /// Write value to command.
 pub fn write_value(
-    mut cmd: [u8; 9],
+    mut cmd: [u8; BODY_LEN],
     value: u16,
-) -> [u8; 9] {
+) -> [u8; BODY_LEN] {
     cmd[7] = (value >> 8 & 0xff) as u8;
     cmd[8] = (value & 0xff) as u8;
     cmd
 }
 
+/// Write value to command, failing if it doesn't fit in the value field.
+///
+/// This is for bodies built by hand, for registers without a typed command:
+///
+/// ```
+/// use cowboy_protocol::checked_write_value;
+/// use cowboy_protocol::error::CmdError;
+/// use cowboy_protocol::frame::Frame;
+///
+/// let body = [0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0];
+/// let frame = Frame::from_body(checked_write_value(body, 25)?);
+///
+/// assert_eq!(
+///     checked_write_value(body, 0x1_0000),
+///     Err(CmdError::ValueTooLarge { value: 0x1_0000, max: 0xFFFF })
+/// );
+/// # Ok::<(), CmdError>(())
+/// ```
+pub fn checked_write_value(
+    cmd: [u8; BODY_LEN],
+    value: u32,
+) -> Result<[u8; BODY_LEN], CmdError> {
+    match u16::try_from(value) {
+        Ok(value) => Ok(write_value(cmd, value)),
+        Err(_) => Err(CmdError::ValueTooLarge {
+            value,
+            max: u16::MAX.into(),
+        }),
+    }
+}
+
+/// Narrow a register value to a byte.
+pub(super) fn value_u8(value: u16) -> Result<u8, FieldError> {
+    u8::try_from(value).map_err(|_| FieldError::InvalidRange {
+        start: 0,
+        end: u8::MAX,
+        field: None,
+    })
+}
+
+/// Builds a frame byte by byte, to experiment with unknown commands.
+///
+/// The device, function and register must be set. The register count
+/// defaults to `1`, and the byte count and value to `0`.
+///
+/// ```
+/// use cowboy_protocol::FrameBuilder;
+///
+/// let frame = FrameBuilder::new()
+///     .device(0x1)
+///     .func(0x10)
+///     .register(0x81)
+///     .byte_count(2)
+///     .value_u16(25)
+///     .build()?;
+/// # Ok::<(), cowboy_protocol::error::CmdError>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FrameBuilder {
+    device: Option<u8>,
+    function: Option<u8>,
+    register: Option<u16>,
+    count: u16,
+    byte_count: u8,
+    value: u16,
+}
+
+impl Default for FrameBuilder {
+    fn default() -> Self {
+        Self {
+            device: None,
+            function: None,
+            register: None,
+            count: 1,
+            byte_count: 0,
+            value: 0,
+        }
+    }
+}
+
+impl FrameBuilder {
+    /// Creates a builder with nothing set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the device addressed (`0xA` or `0x1`).
+    pub fn device(
+        mut self,
+        device: u8,
+    ) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Set the function code (`0x10` write, `0x03` read).
+    pub fn func(
+        mut self,
+        function: u8,
+    ) -> Self {
+        self.function = Some(function);
+        self
+    }
+
+    /// Set the register addressed.
+    pub fn register(
+        mut self,
+        register: u16,
+    ) -> Self {
+        self.register = Some(register);
+        self
+    }
+
+    /// Set the number of registers addressed.
+    pub fn count(
+        mut self,
+        count: u16,
+    ) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Set the number of value bytes (`2` write, `0` read).
+    pub fn byte_count(
+        mut self,
+        byte_count: u8,
+    ) -> Self {
+        self.byte_count = byte_count;
+        self
+    }
+
+    /// Set the value.
+    pub fn value_u16(
+        mut self,
+        value: u16,
+    ) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Build the frame, with its checksum.
+    pub fn build(&self) -> Result<[u8; FRAME_LEN], CmdError> {
+        let missing = |field| CmdError::MissingField { field };
+        let device = self.device.ok_or(missing("device"))?;
+        let function = self.function.ok_or(missing("func"))?;
+        let [reg_hi, reg_lo] = self.register.ok_or(missing("register"))?.to_be_bytes();
+        let [count_hi, count_lo] = self.count.to_be_bytes();
+
+        let body = [
+            device,
+            function,
+            reg_hi,
+            reg_lo,
+            count_hi,
+            count_lo,
+            self.byte_count,
+            0x0,
+            0x0,
+        ];
+        Ok(packetize(&write_value(body, self.value)))
+    }
+}
+
 /// Calculate checksum for command using a modified CRC-16-CCITT
 /// algorithm gotten directly from the uncompiled Cowboy app.
-pub(super) fn checksum(msg: &[u8; 9]) -> [u8; 2] {
+///
+/// Frames are checksummed through [`ChecksumAlgorithm`]; this is kept as a
+/// shorthand for the default algorithm in tests.
+#[cfg(test)]
+pub(super) fn checksum(msg: &[u8; BODY_LEN]) -> [u8; CHECKSUM_LEN] {
+    ChecksumAlgorithm::default().checksum(msg).0
+}
+
+/// Calculate the CRC used by [`checksum`] over data of any length.
+///
+/// With the `crc-table` feature, a precomputed lookup table is used instead
+/// of the bitwise loop. Both produce identical results.
+pub(super) fn crc16(data: &[u8]) -> u16 {
+    if cfg!(feature = "crc-table") {
+        crc16_table(data)
+    } else {
+        crc16_bitwise(data)
+    }
+}
+
+fn crc16_bitwise(data: &[u8]) -> u16 {
     let mut i2 = u16::MAX;
-    for &b in msg {
+    for &b in data {
         i2 ^= b as u16;
         for _ in 0..8 {
             let i4 = i2 & 1;
@@ -47,12 +248,60 @@ pub(super) fn checksum(msg: &[u8; 9]) -> [u8; 2] {
             }
         }
     }
-    [i2 as u8, (i2 >> 8) as u8]
+    i2
+}
+
+/// CRC of every byte value, for the table-driven CRC.
+const CRC_TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                0 => crc >> 1,
+                _ => (crc >> 1) ^ 0xA001,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc16_table(data: &[u8]) -> u16 {
+    data.iter().fold(u16::MAX, |crc, &b| {
+        (crc >> 8) ^ CRC_TABLE[((crc ^ b as u16) & 0xFF) as usize]
+    })
+}
+
+/// Check that the checksum of a frame matches its content.
+pub(super) fn verify_checksum(frame: &[u8; FRAME_LEN]) -> bool {
+    verify_checksum_with(frame, ChecksumAlgorithm::default())
+}
+
+/// Check that the checksum of a frame, computed with `algorithm`, matches
+/// its content.
+pub(super) fn verify_checksum_with(
+    frame: &[u8; FRAME_LEN],
+    algorithm: ChecksumAlgorithm,
+) -> bool {
+    algorithm.checksum(&frame[..BODY_LEN]) == Checksum([frame[BODY_LEN], frame[BODY_LEN + 1]])
 }
 
 /// Packetize the command to be ready for transmission.
-pub(super) fn packetize(cmd: &[u8; 9]) -> [u8; 11] {
-    let checksum = checksum(cmd);
+pub(super) fn packetize(cmd: &[u8; BODY_LEN]) -> [u8; FRAME_LEN] {
+    packetize_with(cmd, ChecksumAlgorithm::default())
+}
+
+/// Packetize the command with the checksum computed by `algorithm`.
+pub(super) fn packetize_with(
+    cmd: &[u8; BODY_LEN],
+    algorithm: ChecksumAlgorithm,
+) -> [u8; FRAME_LEN] {
+    let checksum = algorithm.checksum(cmd).0;
 
     [
         cmd[0],
@@ -71,6 +320,151 @@ pub(super) fn packetize(cmd: &[u8; 9]) -> [u8; 11] {
 
 #[cfg(test)]
 pub(crate) mod test {
+    use super::*;
+    use crate::cmd_body;
+
+    #[test]
+    fn test_checksum_placement() {
+        for body in [
+            [0xA, 0x10, 0x0, 0x1, 0x0, 0x1, 0x2, 0x0, 0x1],
+            [0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x0, 0x0, 0x0],
+            [0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x7F, 0xFF],
+            [0x0; BODY_LEN],
+        ] {
+            let frame = packetize(&body);
+            assert_eq!(frame[..BODY_LEN], body);
+            assert_eq!(frame[BODY_LEN..], checksum(&body));
+        }
+    }
+
+    #[test]
+    fn test_cmd_body_matches_frame() {
+        let read = [0x1, 0x3, 0x0, 0x81, 0x0, 0x1, 0x0, 0x0, 0x0];
+        let write = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x0];
+
+        assert_eq!(cmd!(read)[..BODY_LEN], read);
+
+        for (body, frame) in [
+            (cmd_body!(write, 0x19), cmd!(write, 0x19)),
+            (cmd_body!(write, 0xFFFF), cmd!(write, 0xFFFF)),
+        ] {
+            assert_eq!(frame[..BODY_LEN], body);
+            assert_eq!(frame[BODY_LEN..], checksum(&body));
+        }
+
+        assert_eq!(cmd_body!(write, 0x19)[7..], [0x0, 0x19]);
+    }
+
+    #[test]
+    fn test_frame_builder() {
+        use crate::cmd::SettingsWriteCmd;
+        use crate::types::FieldWeakening;
+
+        let frame = FrameBuilder::new()
+            .device(0x1)
+            .func(0x10)
+            .register(0x81)
+            .count(1)
+            .byte_count(2)
+            .value_u16(25)
+            .build();
+        let expected =
+            SettingsWriteCmd::SetFieldWeakening(FieldWeakening::new(25).unwrap()).try_into();
+        assert_eq!(frame, expected);
+
+        let read = FrameBuilder::new().device(0xA).func(0x3).register(0x4);
+        assert_eq!(
+            read.build(),
+            SettingsWriteCmd::ReadMaxAssistedSpeed.try_into()
+        );
+        assert!(verify_checksum(&read.build().unwrap()));
+
+        assert_eq!(
+            FrameBuilder::new().func(0x3).register(0x4).build(),
+            Err(CmdError::MissingField { field: "device" })
+        );
+        assert_eq!(
+            FrameBuilder::new().device(0x1).register(0x4).build(),
+            Err(CmdError::MissingField { field: "func" })
+        );
+        assert_eq!(
+            FrameBuilder::new().device(0x1).func(0x3).build(),
+            Err(CmdError::MissingField { field: "register" })
+        );
+    }
+
+    #[test]
+    fn test_checked_write_value() {
+        let body = [0x1, 0x10, 0x0, 0x81, 0x0, 0x1, 0x2, 0x0, 0x0];
+
+        assert_eq!(checked_write_value(body, 0x0), Ok(body));
+        assert_eq!(
+            checked_write_value(body, 0xFFFF),
+            Ok(write_value(body, 0xFFFF))
+        );
+        assert_eq!(
+            checked_write_value(body, 0x1_0000),
+            Err(CmdError::ValueTooLarge {
+                value: 0x1_0000,
+                max: 0xFFFF
+            })
+        );
+    }
+
+    #[test]
+    fn test_default_checksum_algorithm() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+        let algorithm = ChecksumAlgorithm::default();
+
+        assert_eq!(algorithm, ChecksumAlgorithm::Crc16Modbus);
+        assert_eq!(algorithm.checksum(&body), Checksum([0x15, 0x2E]));
+        assert_eq!(packetize_with(&body, algorithm), packetize(&body));
+        assert_eq!(packetize(&body)[BODY_LEN..], [0x15, 0x2E]);
+        assert!(verify_checksum_with(&packetize(&body), algorithm));
+    }
+
+    #[test]
+    fn test_big_endian_checksum_algorithm() {
+        let body = [0xA, 0x10, 0x0, 0x4, 0x0, 0x1, 0x2, 0x0, 0x19];
+        let algorithm = ChecksumAlgorithm::Crc16ModbusBigEndian;
+
+        let frame = packetize_with(&body, algorithm);
+        assert_eq!(frame[..BODY_LEN], body);
+        assert_eq!(frame[BODY_LEN..], [0x2E, 0x15]);
+        assert!(verify_checksum_with(&frame, algorithm));
+        assert!(!verify_checksum(&frame));
+
+        let body = [0x1, 0x10, 0x1, 0xFF, 0x0, 0x1, 0x2, 0x7F, 0xFF];
+        let [lo, hi] = packetize(&body)[BODY_LEN..] else {
+            unreachable!()
+        };
+        assert_eq!(packetize_with(&body, algorithm)[BODY_LEN..], [hi, lo]);
+    }
+
+    #[test]
+    fn test_crc_table_matches_bitwise() {
+        for b in 0..=u8::MAX {
+            assert_eq!(crc16_table(&[b]), crc16_bitwise(&[b]));
+        }
+
+        let mut data = [0x0; 1024];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i * 31 % 251) as u8;
+        }
+
+        for len in [0, 1, 9, 11, 255, 1024] {
+            assert_eq!(crc16_table(&data[..len]), crc16_bitwise(&data[..len]));
+        }
+    }
+
+    /// Like [`cmd!`], but returns the body without packetizing it.
+    #[macro_export]
+    macro_rules! cmd_body {
+        ($command: expr, $value: expr) => {
+            $crate::utils::write_value($command, $value)
+        };
+    }
+
     #[macro_export]
     macro_rules! chk {
         ($cmd: expr) => {{
@@ -87,6 +481,21 @@ pub(crate) mod test {
         }};
     }
 
+    /// Encode a setting value to its frame, decode the frame back and assert
+    /// it is the `$variant` command carrying the same value.
+    #[macro_export]
+    macro_rules! roundtrip {
+        ($variant: path, $value: expr) => {{
+            let value = $value;
+            let frame: [u8; 11] = value.try_into().unwrap();
+            assert_eq!(
+                $crate::cmd::SettingsWriteCmd::try_from(frame),
+                Ok($variant(value)),
+                "{value:?}"
+            );
+        }};
+    }
+
     #[macro_export]
     macro_rules! assert_all {
         ($cases: expr) => {
@@ -0,0 +1,45 @@
+//! Builds the crate for a bare-metal target, to make sure no feature pulls
+//! in `std`.
+//!
+//! These tests need the target to be installed
+//! (`rustup target add thumbv7em-none-eabihf`), so they are ignored by
+//! default. Run them with `cargo test --test no_std -- --ignored`.
+
+use std::process::Command;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// Every optional feature of the crate, each built on its own.
+const FEATURES: &[&str] = &["heapless", "alloc", "crc-table", "async", "mock"];
+
+fn build(args: &[&str]) {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--target", TARGET, "--manifest-path"])
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .args(args)
+        .env("CARGO_TARGET_DIR", concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"))
+        .status()
+        .expect("failed to run cargo");
+
+    assert!(status.success(), "no_std build failed with {args:?}");
+}
+
+#[test]
+#[ignore = "requires the thumbv7em-none-eabihf target"]
+fn test_no_std_default_features() {
+    build(&[]);
+}
+
+#[test]
+#[ignore = "requires the thumbv7em-none-eabihf target"]
+fn test_no_std_no_default_features() {
+    build(&["--no-default-features"]);
+}
+
+#[test]
+#[ignore = "requires the thumbv7em-none-eabihf target"]
+fn test_no_std_each_feature() {
+    for feature in FEATURES {
+        build(&["--no-default-features", "--features", feature]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Replays test vectors through the decoders.
+//!
+//! The frames live in `tests/data/frames.txt`, so adding a new vector only
+//! takes a line there.
+
+use cowboy_protocol::error::CmdError;
+use cowboy_protocol::CowboyLockCmd;
+use cowboy_protocol::SettingsWriteCmd;
+use uuid::uuid;
+use uuid::Uuid;
+
+const FIXTURES: &str = include_str!("data/frames.txt");
+
+const SETTINGS_WRITE: Uuid = uuid!("6E400002-B5A3-F393-E0A9-E50E24DCCA9E");
+const LOCK: Uuid = uuid!("C0B0A001-18EB-499D-B266-2F2910744274");
+
+fn parse_frame(hex: &str) -> [u8; 11] {
+    assert_eq!(hex.len(), 22, "frame {hex} isn't 11 bytes long");
+
+    let mut frame = [0; 11];
+    for (i, byte) in frame.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .unwrap_or_else(|_| panic!("frame {hex} isn't valid hex"));
+    }
+    frame
+}
+
+/// Decode a frame, returning the command decoded in `Debug` form along
+/// with the frame it encodes back into.
+fn decode(
+    characteristic: Uuid,
+    frame: [u8; 11],
+) -> Result<(String, [u8; 11]), CmdError> {
+    match characteristic {
+        SETTINGS_WRITE => {
+            let cmd = SettingsWriteCmd::try_from(frame)?;
+            Ok((format!("{cmd:?}"), cmd.try_into()?))
+        }
+        LOCK => {
+            let cmd = CowboyLockCmd::try_from(frame)?;
+            Ok((format!("{cmd:?}"), cmd.try_into()?))
+        }
+        _ => panic!("no decoder for characteristic {characteristic}"),
+    }
+}
+
+#[test]
+fn test_replay_frames() {
+    let fixtures = FIXTURES
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let mut replayed = 0;
+    for (i, line) in fixtures {
+        let mut fields = line.splitn(3, ' ');
+        let (Some(characteristic), Some(frame), Some(expected)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            panic!("line {} is malformed: {line}", i + 1);
+        };
+
+        let characteristic = Uuid::parse_str(characteristic).unwrap();
+        let frame = parse_frame(frame);
+        let decoded = match decode(characteristic, frame) {
+            Ok((cmd, encoded)) => {
+                assert_eq!(encoded, frame, "line {} doesn't round trip", i + 1);
+                format!("Ok({cmd})")
+            }
+            Err(err) => format!("Err({err:?})"),
+        };
+        assert_eq!(decoded, expected, "line {}", i + 1);
+        replayed += 1;
+    }
+
+    assert!(replayed > 0, "no frames were replayed");
+}